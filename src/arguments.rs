@@ -5,38 +5,87 @@ pub enum ArgumentError {
     TwoCommands,
     TwoSysroots,
     TwoPrefixes,
+    TwoJobs,
+    TwoToolchains,
+    TwoProfiles,
+    TwoCacheDirs,
+    TwoBuildDirs,
+    TwoDirectories,
+    TwoFiles,
     InvalidCommand(String),
+    UnknownOption(String),
+    InvalidJobs(String),
+    InvalidDefine(String),
+    BuiltInDefine(String),
+    InvalidSibling(String),
     NoSysrootAfterOption,
     NoPrefixAfterOption,
+    NoJobsAfterOption(String),
+    NoToolchainAfterOption,
+    NoProfileAfterOption,
+    NoCacheDirAfterOption,
+    NoBuildDirAfterOption,
+    NoDirectoryAfterOption,
+    NoFileAfterOption(String),
+    NoDefineAfterOption(String),
+    NoSiblingAfterOption,
 }
 
 pub enum Command {
     Build,
     Install,
     Clean,
+    CompileCommands,
 }
 
 pub struct Options {
     command: Command,
+    help: bool,
+    version: bool,
     verbose: bool,
     quiet: bool,
+    hash: bool,
     sysroot: PathBuf,
     prefix: PathBuf,
+    jobs: usize,
+    toolchain: Option<PathBuf>,
+    profile: String,
+    cache_dir: Option<PathBuf>,
+    build_dir: Option<PathBuf>,
+    directory: Option<PathBuf>,
+    file: Option<PathBuf>,
+    defines: Vec<(String, String)>,
+    siblings: Vec<(String, PathBuf)>,
 }
 
 pub fn parse_arguments(arguments: Vec<String>) -> Result<Options, ArgumentError> {
     let mut command = None;
+    let mut help = false;
+    let mut version = false;
     let mut verbose = false;
     let mut quiet = false;
+    let mut hash = false;
     let mut sysroot = None;
     let mut prefix = None;
+    let mut jobs = None;
+    let mut toolchain = None;
+    let mut profile = None;
+    let mut cache_dir = None;
+    let mut build_dir = None;
+    let mut directory = None;
+    let mut file = None;
+    let mut defines = Vec::new();
+    let mut siblings = Vec::new();
 
     let mut iter = arguments.iter();
     iter.next(); // Ignore first argument
     while let Some(argument) = iter.next() {
         match argument.as_str() {
+            "-h" | "--help" => help = true,
+            "--version" => version = true,
             "-v" | "--verbose" => verbose = true,
             "-q" | "--quiet" => quiet = true,
+            "--hash" => hash = true,
             "--sysroot" => {
                 let new_sysroot = match iter.next() {
                     Some(string) => string,
@@ -59,6 +108,123 @@ pub fn parse_arguments(arguments: Vec<String>) -> Result<Options, ArgumentError>
                     None => prefix = Some(new_prefix),
                 }
             }
+            "-j" | "--jobs" => {
+                let new_jobs = match iter.next() {
+                    Some(string) => match string.parse::<usize>() {
+                        Ok(new_jobs) if new_jobs > 0 => new_jobs,
+                        _ => return Err(ArgumentError::InvalidJobs(string.to_owned())),
+                    },
+                    None => return Err(ArgumentError::NoJobsAfterOption(argument.to_owned())),
+                };
+
+                match jobs {
+                    Some(_) => return Err(ArgumentError::TwoJobs),
+                    None => jobs = Some(new_jobs),
+                }
+            }
+            "--toolchain" => {
+                let new_toolchain = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoToolchainAfterOption),
+                };
+
+                match toolchain {
+                    Some(_) => return Err(ArgumentError::TwoToolchains),
+                    None => toolchain = Some(PathBuf::from(new_toolchain)),
+                }
+            }
+            "--profile" => {
+                let new_profile = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoProfileAfterOption),
+                };
+
+                match profile {
+                    Some(_) => return Err(ArgumentError::TwoProfiles),
+                    None => profile = Some(new_profile),
+                }
+            }
+            "--cache-dir" => {
+                let new_cache_dir = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoCacheDirAfterOption),
+                };
+
+                match cache_dir {
+                    Some(_) => return Err(ArgumentError::TwoCacheDirs),
+                    None => cache_dir = Some(PathBuf::from(new_cache_dir)),
+                }
+            }
+            "--build-dir" => {
+                let new_build_dir = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoBuildDirAfterOption),
+                };
+
+                match build_dir {
+                    Some(_) => return Err(ArgumentError::TwoBuildDirs),
+                    None => build_dir = Some(PathBuf::from(new_build_dir)),
+                }
+            }
+            "-C" => {
+                let new_directory = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoDirectoryAfterOption),
+                };
+
+                match directory {
+                    Some(_) => return Err(ArgumentError::TwoDirectories),
+                    None => directory = Some(PathBuf::from(new_directory)),
+                }
+            }
+            "-f" | "--file" => {
+                let new_file = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoFileAfterOption(argument.to_owned())),
+                };
+
+                match file {
+                    Some(_) => return Err(ArgumentError::TwoFiles),
+                    None => file = Some(PathBuf::from(new_file)),
+                }
+            }
+            "-D" | "--define" => {
+                let define = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoDefineAfterOption(argument.to_owned())),
+                };
+
+                match define.split_once('=') {
+                    // The built-in variables describe the build, so they are
+                    // set through their own options
+                    Some((name, _))
+                        if crate::config::BUILT_IN_VARIABLES.contains(&name)
+                            || name.starts_with("env.") =>
+                    {
+                        return Err(ArgumentError::BuiltInDefine(name.to_owned()))
+                    }
+                    Some((name, value)) if !name.is_empty() => {
+                        defines.push((name.to_owned(), value.to_owned()))
+                    }
+                    _ => return Err(ArgumentError::InvalidDefine(define.to_owned())),
+                }
+            }
+            "--sibling" => {
+                let sibling = match iter.next() {
+                    Some(string) => string,
+                    None => return Err(ArgumentError::NoSiblingAfterOption),
+                };
+
+                match sibling.split_once('=') {
+                    Some((name, directory)) if !name.is_empty() && !directory.is_empty() => {
+                        siblings.push((name.to_owned(), PathBuf::from(directory)))
+                    }
+                    _ => return Err(ArgumentError::InvalidSibling(sibling.to_owned())),
+                }
+            }
+            _ if argument.starts_with('-') => {
+                return Err(ArgumentError::UnknownOption(argument.to_owned()))
+            }
             _ => {
                 let new_command = Command::parse(argument)?;
                 match command {
@@ -74,8 +240,11 @@ pub fn parse_arguments(arguments: Vec<String>) -> Result<Options, ArgumentError>
             Some(command) => command,
             None => Command::Build,
         },
+        help,
+        version,
         verbose,
         quiet,
+        hash,
         sysroot: PathBuf::from(match sysroot {
             Some(sysroot) => sysroot,
             None => crate::config::DEFAULT_SYSROOT,
@@ -84,15 +253,116 @@ pub fn parse_arguments(arguments: Vec<String>) -> Result<Options, ArgumentError>
             Some(prefix) => prefix,
             None => crate::config::DEFAULT_PREFIX,
         }),
+        jobs: match jobs {
+            Some(jobs) => jobs,
+            None => match std::thread::available_parallelism() {
+                Ok(jobs) => jobs.get(),
+                Err(_) => 1,
+            },
+        },
+        toolchain,
+        profile: match profile {
+            Some(profile) => profile.to_owned(),
+            None => crate::config::DEFAULT_PROFILE.to_owned(),
+        },
+        // Fall back to the environment so a cache can be shared without
+        // passing the option to every build
+        cache_dir: match cache_dir {
+            Some(cache_dir) => Some(cache_dir),
+            None => match std::env::var_os(crate::config::CACHE_DIR_VARIABLE) {
+                Some(cache_dir) if !cache_dir.is_empty() => Some(PathBuf::from(cache_dir)),
+                _ => None,
+            },
+        },
+        build_dir,
+        directory,
+        file,
+        defines,
+        siblings,
     })
 }
 
+pub fn version() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+pub fn usage() -> String {
+    format!(
+        "Usage: brew [OPTIONS] [COMMAND]
+
+Commands:
+  build                   Compile and link the project (default)
+  install                 Build, then install the target, objects and headers
+  clean                   Remove objects and targets
+  compile-commands        Write {compile_commands}
+
+Options:
+  -h, --help              Print this help and exit
+      --version           Print the version and exit
+  -v, --verbose           Print the options, brewfile and toolchain
+  -q, --quiet             Only print errors
+  -C <DIR>                Change to DIR before doing anything else
+  -f, --file <FILE>       Read FILE instead of {brewfile}
+  -j, --jobs <N>          Compile N files at once (default: number of CPUs)
+  -D, --define <NAME=VALUE>
+                          Set the brewfile variable NAME, overriding \"let\"
+      --sysroot <DIR>     System root (default: {sysroot})
+      --prefix <DIR>      Install prefix (default: {prefix})
+      --profile <NAME>    Build profile (default: {profile})
+      --toolchain <FILE>  Toolchain file overriding the built-in toolchain
+      --build-dir <DIR>   Write objects, targets and build state to DIR
+      --cache-dir <DIR>   Share compiled objects through DIR (or ${cache_variable})
+      --hash              Compare content hashes instead of modification times
+      --sibling <NAME=DIR>
+                          Link the dependency NAME from DIR (passed on by groups)
+
+Brewfile keys:
+  name = NAME
+  type = executable | library | shared_library | binary | group
+  languages = LANGUAGE, ...            c, cpp, assembly
+  dependencies = NAME, ...
+  version = VERSION
+  cflags | cxxflags | asmflags | ldflags = FLAG, ...
+  defines = NAME[=VALUE], ...
+  include_dirs = DIR, ...
+  profile.PROFILE.FLAGS = VALUE, ...   FLAGS is any of the flag keys above
+  extension.EXTENSION = LANGUAGE | ignore
+  linker_script = FILE
+  entry = SYMBOL
+  output = FILE
+  install_dir = DIR
+  build_dir = DIR
+  toolchain = FILE
+  priority = DIR, ...
+  OBJECT = LANGUAGE, SOURCE, INSTALL_TARGET
+  let VARIABLE = VALUE, ...
+  if VARIABLE ==|!= VALUE ... [else ...] end
+  include = FILE, ...                  FILE is relative to the including file
+
+Values containing spaces or punctuation can be written \"in quotes\", using
+\\\" \\\\ \\n \\t and \\r for quotes, backslashes, newlines, tabs and returns.
+
+${{VARIABLE}} is replaced by a variable's value, a parameter which is only a
+reference being replaced by every value. The built-in variables are sysroot,
+prefix, profile, name and env.NAME for environment variables. Write $$ or \\$
+for a literal $.
+",
+        compile_commands = crate::config::COMPILE_COMMANDS_NAME,
+        brewfile = crate::config::BREWFILE_NAME,
+        sysroot = crate::config::DEFAULT_SYSROOT,
+        prefix = crate::config::DEFAULT_PREFIX,
+        profile = crate::config::DEFAULT_PROFILE,
+        cache_variable = crate::config::CACHE_DIR_VARIABLE,
+    )
+}
+
 impl Command {
     pub fn parse(string: &str) -> Result<Self, ArgumentError> {
         match string {
             "clean" => Ok(Command::Clean),
             "build" => Ok(Command::Build),
             "install" => Ok(Command::Install),
+            "compile-commands" => Ok(Command::CompileCommands),
             _ => Err(ArgumentError::InvalidCommand(string.to_owned())),
         }
     }
@@ -107,6 +377,7 @@ impl std::fmt::Display for Command {
                 Command::Build => "build",
                 Command::Install => "install",
                 Command::Clean => "clean",
+                Command::CompileCommands => "compile-commands",
             }
         )
     }
@@ -123,18 +394,63 @@ impl std::fmt::Display for ArgumentError {
                 ArgumentError::TwoCommands => format!("Attempting to specify two commands"),
                 ArgumentError::TwoSysroots => format!("Attempting to specify two system roots"),
                 ArgumentError::TwoPrefixes => format!("Attempting to specify two prefixes"),
+                ArgumentError::TwoJobs => "Attempting to specify two job counts".to_string(),
+                ArgumentError::TwoToolchains => "Attempting to specify two toolchains".to_string(),
+                ArgumentError::TwoProfiles => "Attempting to specify two profiles".to_string(),
+                ArgumentError::TwoCacheDirs =>
+                    "Attempting to specify two cache directories".to_string(),
+                ArgumentError::TwoBuildDirs =>
+                    "Attempting to specify two build directories".to_string(),
+                ArgumentError::TwoDirectories =>
+                    "Attempting to specify two directories to change to".to_string(),
+                ArgumentError::TwoFiles => "Attempting to specify two brewfiles".to_string(),
                 ArgumentError::InvalidCommand(command) =>
                     format!("Unknown command \"{}\"", command),
+                ArgumentError::UnknownOption(option) =>
+                    format!("Unknown option \"{}\" (see \"brew --help\")", option),
+                ArgumentError::InvalidJobs(jobs) => format!("Invalid number of jobs \"{}\"", jobs),
+                ArgumentError::InvalidDefine(define) =>
+                    format!("Invalid definition \"{}\" (expected NAME=VALUE)", define),
+                ArgumentError::BuiltInDefine(name) =>
+                    format!("Unable to define the built-in variable \"{}\"", name),
+                ArgumentError::InvalidSibling(sibling) =>
+                    format!("Invalid sibling \"{}\" (expected NAME=DIR)", sibling),
                 ArgumentError::NoSysrootAfterOption =>
                     format!("Nothing specified after \"--sysroot\""),
                 ArgumentError::NoPrefixAfterOption =>
                     format!("Nothing specified after \"--prefix\""),
+                ArgumentError::NoJobsAfterOption(option) =>
+                    format!("Nothing specified after \"{}\"", option),
+                ArgumentError::NoToolchainAfterOption =>
+                    "Nothing specified after \"--toolchain\"".to_string(),
+                ArgumentError::NoProfileAfterOption =>
+                    "Nothing specified after \"--profile\"".to_string(),
+                ArgumentError::NoCacheDirAfterOption =>
+                    "Nothing specified after \"--cache-dir\"".to_string(),
+                ArgumentError::NoBuildDirAfterOption =>
+                    "Nothing specified after \"--build-dir\"".to_string(),
+                ArgumentError::NoDirectoryAfterOption =>
+                    "Nothing specified after \"-C\"".to_string(),
+                ArgumentError::NoFileAfterOption(option) =>
+                    format!("Nothing specified after \"{}\"", option),
+                ArgumentError::NoDefineAfterOption(option) =>
+                    format!("Nothing specified after \"{}\"", option),
+                ArgumentError::NoSiblingAfterOption =>
+                    "Nothing specified after \"--sibling\"".to_string(),
             }
         )
     }
 }
 
 impl Options {
+    pub fn help(&self) -> bool {
+        self.help
+    }
+
+    pub fn version(&self) -> bool {
+        self.version
+    }
+
     pub fn verbose(&self) -> bool {
         self.verbose
     }
@@ -143,6 +459,12 @@ impl Options {
         self.quiet
     }
 
+    // Returns true if up to date checks compare content hashes instead of
+    // modification times
+    pub fn hash(&self) -> bool {
+        self.hash
+    }
+
     pub fn command(&self) -> &Command {
         &self.command
     }
@@ -154,6 +476,81 @@ impl Options {
     pub fn prefix(&self) -> &Path {
         &self.prefix
     }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    pub fn toolchain(&self) -> Option<&Path> {
+        self.toolchain.as_deref()
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    pub fn build_dir(&self) -> Option<&Path> {
+        self.build_dir.as_deref()
+    }
+
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    // Returns the directories group builds put their projects' targets in,
+    // by project name
+    pub fn siblings(&self) -> &[(String, PathBuf)] {
+        &self.siblings
+    }
+
+    pub fn sibling(&self, name: &str) -> Option<&Path> {
+        self.siblings
+            .iter()
+            .find(|(sibling, _)| sibling == name)
+            .map(|(_, directory)| directory.as_path())
+    }
+
+    // Makes the relative paths given on the command line relative to
+    // "directory" so they keep their meaning after changing directory
+    pub fn resolve_paths(&mut self, directory: &Path) {
+        for path in [&mut self.sysroot, &mut self.prefix] {
+            if path.is_relative() {
+                *path = directory.join(&path);
+            }
+        }
+
+        for path in [
+            &mut self.toolchain,
+            &mut self.cache_dir,
+            &mut self.build_dir,
+            &mut self.file,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if path.is_relative() {
+                *path = directory.join(&path);
+            }
+        }
+
+        for (_, path) in &mut self.siblings {
+            if path.is_relative() {
+                *path = directory.join(&path);
+            }
+        }
+    }
 }
 
 impl std::fmt::Display for Options {
@@ -161,6 +558,31 @@ impl std::fmt::Display for Options {
         writeln!(f, "Command: {}", self.command)?;
         writeln!(f, "Verbose: {}", self.verbose)?;
         writeln!(f, "System Root: {}", self.sysroot.to_string_lossy())?;
-        writeln!(f, "Prefix: {}", self.prefix.to_string_lossy())
+        writeln!(f, "Prefix: {}", self.prefix.to_string_lossy())?;
+        writeln!(f, "Jobs: {}", self.jobs)?;
+        writeln!(f, "Hash: {}", self.hash)?;
+        writeln!(f, "Profile: {}", self.profile)?;
+        if let Some(toolchain) = &self.toolchain {
+            writeln!(f, "Toolchain: {}", toolchain.to_string_lossy())?;
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            writeln!(f, "Cache Directory: {}", cache_dir.to_string_lossy())?;
+        }
+        if let Some(build_dir) = &self.build_dir {
+            writeln!(f, "Build Directory: {}", build_dir.to_string_lossy())?;
+        }
+        if let Some(directory) = &self.directory {
+            writeln!(f, "Directory: {}", directory.to_string_lossy())?;
+        }
+        if let Some(file) = &self.file {
+            writeln!(f, "Brewfile: {}", file.to_string_lossy())?;
+        }
+        for (name, value) in &self.defines {
+            writeln!(f, "Define: {}={}", name, value)?;
+        }
+        for (name, directory) in &self.siblings {
+            writeln!(f, "Sibling: {}={}", name, directory.to_string_lossy())?;
+        }
+        Ok(())
     }
 }
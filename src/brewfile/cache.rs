@@ -0,0 +1,165 @@
+use super::{dependency_file, hash::Sha256, job::Job};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+// A content addressed store of objects and their dependency files, shared
+// between projects and checkouts. Entries are keyed on the compiler identity,
+// the flags and the preprocessed source, along with the working directory
+// when it is recorded in the object.
+pub struct Cache {
+    directory: PathBuf,
+    identities: Mutex<BTreeMap<String, Option<String>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    temporaries: AtomicUsize,
+}
+
+// Returns true if the flags ask for debug information, which is on unless
+// the last "-g" flag is "-g0"
+fn has_debug_information(flags: &[String]) -> bool {
+    match flags.iter().rev().find(|flag| flag.starts_with("-g")) {
+        Some(flag) => flag != "-g0",
+        None => false,
+    }
+}
+
+impl Cache {
+    pub fn new(directory: PathBuf) -> Self {
+        Cache {
+            directory,
+            identities: Mutex::new(BTreeMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            temporaries: AtomicUsize::new(0),
+        }
+    }
+
+    // Returns a hash of the compiler's version output, so that upgrading the
+    // compiler invalidates its entries
+    fn identity(&self, program: &str) -> Option<String> {
+        if let Some(identity) = self.identities.lock().unwrap().get(program) {
+            return identity.clone();
+        }
+
+        let identity = match Command::new(program).arg("--version").output() {
+            Ok(output) if output.status.success() => {
+                let mut hasher = Sha256::new();
+                hasher.write(program.as_bytes());
+                hasher.write(&[0]);
+                hasher.write(&output.stdout);
+                hasher.write(&output.stderr);
+                Some(hasher.finish())
+            }
+            _ => None,
+        };
+
+        self.identities
+            .lock()
+            .unwrap()
+            .insert(program.to_owned(), identity.clone());
+        identity
+    }
+
+    // Returns the key for the job, or None if the source can't be
+    // preprocessed, in which case the job is run without the cache
+    pub fn key(&self, job: &Job) -> Option<String> {
+        let identity = self.identity(job.program())?;
+
+        let preprocessed = match job.preprocess_command().output() {
+            Ok(output) if output.status.success() => output.stdout,
+            _ => return None,
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.write(identity.as_bytes());
+        for flag in job.get_flags() {
+            hasher.write(flag.as_bytes());
+            hasher.write(&[0]);
+        }
+
+        // Debug information contains the directory the object was compiled
+        // in, so those objects are only shared within one checkout
+        if has_debug_information(job.get_flags()) {
+            let directory = std::env::current_dir().ok()?;
+            hasher.write(directory.to_string_lossy().as_bytes());
+            hasher.write(&[0]);
+        }
+
+        hasher.write(&preprocessed);
+
+        Some(hasher.finish())
+    }
+
+    fn entry(&self, key: &str, extension: &str) -> PathBuf {
+        self.directory
+            .join(&key[..2])
+            .join(key)
+            .with_extension(extension)
+    }
+
+    // Copies the cached object and dependency file into place. Returns false
+    // on a miss.
+    pub fn restore(&self, job: &Job, key: &str) -> bool {
+        let object = self.entry(key, "o");
+        let dependencies = self.entry(key, "d");
+
+        let restored = object.exists()
+            && dependencies.exists()
+            && std::fs::copy(&object, job.object()).is_ok()
+            && std::fs::copy(&dependencies, dependency_file::path(job.object())).is_ok();
+
+        match restored {
+            true => self.hits.fetch_add(1, Ordering::Relaxed),
+            false => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        restored
+    }
+
+    // Adds the output of a successful job. Entries are written to a temporary
+    // file and renamed so concurrent builds never see a partial entry.
+    // Failures are ignored as the cache is only an optimisation.
+    pub fn store(&self, job: &Job, key: &str) {
+        let parent = self.directory.join(&key[..2]);
+        if std::fs::create_dir_all(&parent).is_err() {
+            return;
+        }
+
+        self.store_file(job.object(), &self.entry(key, "o"));
+        self.store_file(&dependency_file::path(job.object()), &self.entry(key, "d"));
+    }
+
+    fn store_file(&self, source: &Path, destination: &Path) {
+        let temporary = destination.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            self.temporaries.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        match std::fs::copy(source, &temporary) {
+            Ok(_) => match std::fs::rename(&temporary, destination) {
+                Ok(()) => {}
+                Err(_) => {
+                    std::fs::remove_file(&temporary).ok();
+                }
+            },
+            Err(_) => {
+                std::fs::remove_file(&temporary).ok();
+            }
+        }
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
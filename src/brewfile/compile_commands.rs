@@ -0,0 +1,440 @@
+use super::{error::Error, job::Job};
+use std::{
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+// A JSON value, enough to read compilation databases back in. Numbers are
+// kept as they were written.
+enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len() + 2);
+    escaped.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// Objects are written one field per line, arrays on a single line
+fn format_value(value: &Value, indent: usize) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(number) => number.clone(),
+        Value::String(string) => escape(string),
+        Value::Array(values) => {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| format_value(value, indent))
+                .collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Object(fields) if fields.is_empty() => "{}".to_owned(),
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{:indent$}{}: {}",
+                        "",
+                        escape(key),
+                        format_value(value, indent + 2),
+                        indent = indent + 2
+                    )
+                })
+                .collect();
+            format!(
+                "{{\n{}\n{:indent$}}}",
+                fields.join(",\n"),
+                "",
+                indent = indent
+            )
+        }
+    }
+}
+
+fn entry(job: &Job, directory: &str) -> Value {
+    let path = |path: &Path| Value::String(path.to_string_lossy().into_owned());
+
+    Value::Object(vec![
+        ("directory".to_owned(), Value::String(directory.to_owned())),
+        ("file".to_owned(), path(job.source())),
+        (
+            "arguments".to_owned(),
+            Value::Array(job.command_line().into_iter().map(Value::String).collect()),
+        ),
+        ("output".to_owned(), path(job.object())),
+    ])
+}
+
+fn write_entries(path: &Path, entries: Vec<Value>) -> Result<(), Error> {
+    let contents = if entries.is_empty() {
+        "[]\n".to_owned()
+    } else {
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|entry| format!("  {}", format_value(entry, 2)))
+            .collect();
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    };
+
+    match std::fs::write(path, contents) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(Error::WriteCompileCommandsError(path.to_owned(), error)),
+    }
+}
+
+// Writes a compilation database listing every job
+pub fn write(path: &Path, jobs: &[Job]) -> Result<(), Error> {
+    let directory = match std::env::current_dir() {
+        Ok(directory) => directory,
+        Err(error) => return Err(Error::CurrentDirectoryError(error)),
+    };
+    let directory = directory.to_string_lossy();
+
+    write_entries(
+        path,
+        jobs.iter().map(|job| entry(job, &directory)).collect(),
+    )
+}
+
+// Merges the compilation databases written by sub-projects into one
+pub fn merge(path: &Path, databases: &[PathBuf]) -> Result<(), Error> {
+    let mut entries = Vec::new();
+    for database in databases {
+        let contents = match std::fs::read_to_string(database) {
+            Ok(contents) => contents,
+            Err(error) => return Err(Error::ReadCompileCommandsError(database.clone(), error)),
+        };
+
+        match parse(&contents) {
+            Ok(mut database_entries) => entries.append(&mut database_entries),
+            Err(error) => return Err(Error::ParseCompileCommandsError(database.clone(), error)),
+        }
+    }
+
+    write_entries(path, entries)
+}
+
+// Parses a compilation database, which is an array of entries
+fn parse(contents: &str) -> Result<Vec<Value>, &'static str> {
+    let mut parser = Parser {
+        chars: contents.chars().peekable(),
+    };
+
+    let entries = match parser.value()? {
+        Value::Array(entries) => entries,
+        _ => return Err("expected an array of entries"),
+    };
+
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        Some(_) => Err("unexpected text after the entries"),
+        None => Ok(entries),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    // Skips whitespace and consumes "expected" if it is next
+    fn consume(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, &'static str> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                if self.consume(']') {
+                    return Ok(Value::Array(values));
+                }
+
+                loop {
+                    values.push(self.value()?);
+                    if self.consume(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    if !self.consume(',') {
+                        return Err("expected ',' or ']'");
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut fields = Vec::new();
+                if self.consume('}') {
+                    return Ok(Value::Object(fields));
+                }
+
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    if !self.consume(':') {
+                        return Err("expected ':'");
+                    }
+                    fields.push((key, self.value()?));
+
+                    if self.consume('}') {
+                        return Ok(Value::Object(fields));
+                    }
+                    if !self.consume(',') {
+                        return Err("expected ',' or '}'");
+                    }
+                }
+            }
+            Some('-' | '0'..='9') => {
+                let mut number = String::new();
+                while let Some(c @ ('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) = self.chars.peek() {
+                    number.push(*c);
+                    self.chars.next();
+                }
+                Ok(Value::Number(number))
+            }
+            Some('t' | 'f' | 'n') => {
+                let mut word = String::new();
+                while let Some(c @ 'a'..='z') = self.chars.peek() {
+                    word.push(*c);
+                    self.chars.next();
+                }
+
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    _ => Err("unknown value"),
+                }
+            }
+            Some(_) => Err("unknown value"),
+            None => Err("unexpected end of file"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        if self.chars.next() != Some('"') {
+            return Err("expected a string");
+        }
+
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => string.push(self.unicode_escape()?),
+                    _ => return Err("unknown escape sequence"),
+                },
+                Some(c) => string.push(c),
+                None => return Err("unterminated string"),
+            }
+        }
+    }
+
+    // Reads the digits of a "\u" escape, along with the second half of a
+    // surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        let high = self.hex_digits()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or("invalid unicode escape");
+        }
+
+        if self.chars.next() != Some('\\') || self.chars.next() != Some('u') {
+            return Err("invalid unicode escape");
+        }
+        let low = self.hex_digits()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err("invalid unicode escape");
+        }
+
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+            .ok_or("invalid unicode escape")
+    }
+
+    fn hex_digits(&mut self) -> Result<u32, &'static str> {
+        let mut value = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => value = value * 16 + digit,
+                None => return Err("invalid unicode escape"),
+            }
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, write};
+    use crate::brewfile::{error::Error, job::Job};
+    use std::path::{Path, PathBuf};
+
+    // Returns an empty directory for one test
+    fn directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "brew-compile-commands-{}-{}",
+            std::process::id(),
+            test
+        ));
+        std::fs::remove_dir_all(&directory).ok();
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn job(source: &str, arguments: &[&str]) -> Job {
+        let source = Path::new(source);
+        let mut job = Job::new(
+            source,
+            &source.with_extension("c.o"),
+            "Compiling",
+            "C compiler",
+            "clang",
+        );
+        for argument in arguments {
+            job.arg(argument);
+        }
+        job
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_escaped_arguments() {
+        let directory = directory("escapes");
+        let written = directory.join("written.json");
+        let merged = directory.join("merged.json");
+        let jobs = [
+            job("src/a.c", &["-DNAME=\"a b\"", "-DDIR=C:\\dir", "-c"]),
+            job(
+                "src/tab\there.c",
+                &["-DLINE=one\ntwo", "-DBELL=\u{7}", "-DNAME=é"],
+            ),
+        ];
+
+        write(&written, &jobs).unwrap();
+        merge(&merged, std::slice::from_ref(&written)).unwrap();
+
+        let contents = read(&merged);
+        assert_eq!(contents, read(&written));
+        assert!(contents.contains(r#""-DNAME=\"a b\"""#));
+        assert!(contents.contains(r#""-DDIR=C:\\dir""#));
+        assert!(contents.contains(r#""src/tab\there.c""#));
+        assert!(contents.contains(r#""-DLINE=one\ntwo""#));
+        assert!(contents.contains(r#""-DBELL=\u0007""#));
+        assert!(contents.contains(r#""-DNAME=é""#));
+
+        std::fs::remove_dir_all(directory).ok();
+    }
+
+    #[test]
+    fn merge_skips_empty_databases() {
+        let directory = directory("empty");
+        let empty = directory.join("empty.json");
+        let written = directory.join("written.json");
+        let merged = directory.join("merged.json");
+
+        write(&empty, &[]).unwrap();
+        assert_eq!(read(&empty), "[]\n");
+
+        merge(&merged, &[empty.clone(), empty.clone()]).unwrap();
+        assert_eq!(read(&merged), "[]\n");
+
+        write(&written, &[job("src/a.c", &["-c"])]).unwrap();
+        merge(&merged, &[empty.clone(), written.clone(), empty]).unwrap();
+        assert_eq!(read(&merged), read(&written));
+
+        std::fs::remove_dir_all(directory).ok();
+    }
+
+    #[test]
+    fn merge_reads_other_values() {
+        let directory = directory("values");
+        let database = directory.join("database.json");
+        let merged = directory.join("merged.json");
+        std::fs::write(
+            &database,
+            r#"[{"file": "\ud83d\ude00.c", "count": -1.5e3, "ok": true, "none": null, "empty": {}}]"#,
+        )
+        .unwrap();
+
+        merge(&merged, &[database]).unwrap();
+
+        assert_eq!(
+            read(&merged),
+            "[\n  {\n    \"file\": \"\u{1f600}.c\",\n    \"count\": -1.5e3,\n    \"ok\": true,\n    \"none\": null,\n    \"empty\": {}\n  }\n]\n"
+        );
+
+        std::fs::remove_dir_all(directory).ok();
+    }
+
+    #[test]
+    fn merge_rejects_malformed_databases() {
+        let directory = directory("malformed");
+        let database = directory.join("database.json");
+        let merged = directory.join("merged.json");
+
+        for contents in [
+            "",
+            "{}",
+            "[",
+            "[1,]",
+            r#"[{"file" "a.c"}]"#,
+            r#"["unterminated]"#,
+            r#"["\q"]"#,
+            r#"["\ud83d"]"#,
+            "[] []",
+        ] {
+            std::fs::write(&database, contents).unwrap();
+            match merge(&merged, std::slice::from_ref(&database)) {
+                Err(Error::ParseCompileCommandsError(path, _)) => assert_eq!(path, database),
+                result => panic!("{:?} for {:?}", result, contents),
+            }
+        }
+
+        std::fs::remove_dir_all(directory).ok();
+    }
+}
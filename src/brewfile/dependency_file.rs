@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+
+// Returns the path of the dependency file generated alongside an object
+pub fn path(object: &Path) -> PathBuf {
+    object.with_extension("d")
+}
+
+// Parses a make-style dependency file ("target: dep1 dep2 \") and returns
+// the prerequisites, or None if the file cannot be read
+pub fn parse(path: &Path) -> Option<Vec<PathBuf>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return None,
+    };
+
+    let mut dependencies = Vec::new();
+    let mut current = String::new();
+    let mut in_target = true;
+
+    let mut iter = contents.chars().peekable();
+    while let Some(c) = iter.next() {
+        match c {
+            '\\' => match iter.next() {
+                // Line continuation
+                Some('\n') => push_dependency(&mut current, &mut dependencies, in_target),
+                Some('\r') => {
+                    if iter.peek() == Some(&'\n') {
+                        iter.next();
+                    }
+                    push_dependency(&mut current, &mut dependencies, in_target);
+                }
+                // Escaped character
+                Some(' ') => current.push(' '),
+                Some('#') => current.push('#'),
+                Some(c) => {
+                    current.push('\\');
+                    current.push(c);
+                }
+                None => current.push('\\'),
+            },
+            ':' if in_target && iter.peek().map(|c| c.is_whitespace()).unwrap_or(true) => {
+                current.clear();
+                in_target = false;
+            }
+            '\n' => {
+                push_dependency(&mut current, &mut dependencies, in_target);
+                in_target = true;
+            }
+            c if c.is_whitespace() => push_dependency(&mut current, &mut dependencies, in_target),
+            _ => current.push(c),
+        }
+    }
+    push_dependency(&mut current, &mut dependencies, in_target);
+
+    Some(dependencies)
+}
+
+fn push_dependency(current: &mut String, dependencies: &mut Vec<PathBuf>, in_target: bool) {
+    if !current.is_empty() && !in_target {
+        dependencies.push(PathBuf::from(current.as_str()));
+    }
+
+    current.clear();
+}
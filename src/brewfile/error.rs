@@ -6,22 +6,66 @@ pub enum Error {
     NoBrewType,
     NameDefinedTwice,
     BrewTypeDefinedTwice,
+    ToolchainDefinedTwice,
+    VersionDefinedTwice,
+    LinkerScriptDefinedTwice,
+    EntryDefinedTwice,
+    OutputDefinedTwice,
+    InstallDirDefinedTwice,
+    BuildDirDefinedTwice,
+    OutputNotSupported(String),
+    VersionNotSupported(String),
     LanguageDefinedTwice(Language),
     DependencyDefinedTwice(String),
+    DependencyNotFound(String, Vec<PathBuf>),
     UnknownLanguage(String),
+    UnknownProfile(String),
     DirectoryCreationError(PathBuf, std::io::Error),
     DirectoryReadError(PathBuf, std::io::Error),
-    UncompiledFile(PathBuf),
+    UncompiledFile(PathBuf, Vec<String>),
     RemoveObjectsDirectoryError(std::io::Error),
     RemoveTargetError(String, std::io::Error),
+    RemoveDirectoryError(PathBuf, std::io::Error),
     RunCompilerError(&'static str, std::io::Error),
     CompileError(PathBuf),
     RunLinkerError(std::io::Error),
     LinkerError,
+    RunObjcopyError(std::io::Error),
+    ObjcopyError,
     RunBrewError(std::io::Error),
     BrewError(PathBuf),
+    SubBrewfileError(PathBuf, String),
+    ProjectNameConflict(String, PathBuf, PathBuf),
+    DependencyCycle(Vec<String>),
     NoName,
     InstallTargetError(String, std::io::Error),
+    SymlinkError(PathBuf, std::io::Error),
+    CurrentDirectoryError(std::io::Error),
+    ChangeDirectoryError(PathBuf, std::io::Error),
+    WriteStateError(PathBuf, std::io::Error),
+    WriteCompileCommandsError(PathBuf, std::io::Error),
+    ReadCompileCommandsError(PathBuf, std::io::Error),
+    ParseCompileCommandsError(PathBuf, &'static str),
+}
+
+impl Error {
+    // Returns the brewfile key the error is about, if any
+    pub fn key(&self) -> Option<&'static str> {
+        Some(match self {
+            Error::NameDefinedTwice => "name",
+            Error::BrewTypeDefinedTwice => "type",
+            Error::ToolchainDefinedTwice => "toolchain",
+            Error::VersionDefinedTwice | Error::VersionNotSupported(_) => "version",
+            Error::LinkerScriptDefinedTwice => "linker_script",
+            Error::EntryDefinedTwice => "entry",
+            Error::OutputDefinedTwice | Error::OutputNotSupported(_) => "output",
+            Error::InstallDirDefinedTwice => "install_dir",
+            Error::BuildDirDefinedTwice => "build_dir",
+            Error::LanguageDefinedTwice(_) => "languages",
+            Error::DependencyDefinedTwice(_) => "dependencies",
+            _ => return None,
+        })
+    }
 }
 
 impl std::error::Error for Error {}
@@ -37,6 +81,24 @@ impl std::fmt::Display for Error {
                     format!("Attempting to specify more than one name in brewfile"),
                 Error::BrewTypeDefinedTwice =>
                     format!("Attempting to specificy for than one brew type in brewfile"),
+                Error::ToolchainDefinedTwice =>
+                    "Attempting to specify more than one toolchain in brewfile".to_string(),
+                Error::VersionDefinedTwice =>
+                    "Attempting to specify more than one version in brewfile".to_string(),
+                Error::LinkerScriptDefinedTwice =>
+                    "Attempting to specify more than one linker script in brewfile".to_string(),
+                Error::EntryDefinedTwice =>
+                    "Attempting to specify more than one entry point in brewfile".to_string(),
+                Error::OutputDefinedTwice =>
+                    "Attempting to specify more than one output in brewfile".to_string(),
+                Error::InstallDirDefinedTwice =>
+                    "Attempting to specify more than one install directory in brewfile".to_string(),
+                Error::BuildDirDefinedTwice =>
+                    "Attempting to specify more than one build directory in brewfile".to_string(),
+                Error::OutputNotSupported(brew_type) =>
+                    format!("The output of a {} cannot be renamed", brew_type),
+                Error::VersionNotSupported(brew_type) =>
+                    format!("{} projects cannot have a version", brew_type),
                 Error::LanguageDefinedTwice(language) => format!(
                     "Attempting to specify language '{}' twice in brewfile",
                     language
@@ -45,8 +107,18 @@ impl std::fmt::Display for Error {
                     "Attempting to specify dependency '{}' twice in brewfile",
                     dependency
                 ),
+                Error::DependencyNotFound(dependency, searched) => format!(
+                    "Unable to find dependency '{}' (searched {})",
+                    dependency,
+                    searched
+                        .iter()
+                        .map(|path| format!("{}", path.to_string_lossy()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
                 Error::UnknownLanguage(language) =>
                     format!("Unknown language \"{}\" in brewfile", language),
+                Error::UnknownProfile(profile) => format!("Unknown profile \"{}\"", profile),
                 Error::DirectoryCreationError(path, error) => format!(
                     "Unable to create directory {} ({})",
                     path.to_string_lossy(),
@@ -57,14 +129,20 @@ impl std::fmt::Display for Error {
                     path.to_string_lossy(),
                     error
                 ),
-                Error::UncompiledFile(path) => format!(
-                    "Could not find appropriate language for {}",
-                    path.to_string_lossy()
+                Error::UncompiledFile(path, extensions) => format!(
+                    "Could not find appropriate language for {} (recognised extensions: {})",
+                    path.to_string_lossy(),
+                    extensions.join(", ")
                 ),
                 Error::RemoveObjectsDirectoryError(error) =>
                     format!("Failed to remove objects directory ({})", error),
                 Error::RemoveTargetError(target, error) =>
                     format!("Failed to remove {} ({})", target, error),
+                Error::RemoveDirectoryError(directory, error) => format!(
+                    "Failed to remove directory {} ({})",
+                    directory.to_string_lossy(),
+                    error
+                ),
                 Error::RunCompilerError(compiler, error) =>
                     format!("Unable to run {} compiler ({})", compiler, error),
                 Error::CompileError(file) =>
@@ -72,10 +150,48 @@ impl std::fmt::Display for Error {
                 Error::NoName => format!("No name specified in brewfile"),
                 Error::RunLinkerError(error) => format!("Unable to run linker ({})", error),
                 Error::LinkerError => format!("Error while linkning"),
+                Error::RunObjcopyError(error) => format!("Unable to run objcopy ({})", error),
+                Error::ObjcopyError => "Error while converting image to binary".to_string(),
                 Error::RunBrewError(error) => format!("Unable to run brew ({})", error),
                 Error::BrewError(path) => format!("Error while brewing {}", path.to_string_lossy()),
+                Error::SubBrewfileError(path, error) => format!(
+                    "Unable to read brewfile in {} ({})",
+                    path.to_string_lossy(),
+                    error
+                ),
+                Error::ProjectNameConflict(name, first, second) => format!(
+                    "Both {} and {} are named '{}'",
+                    first.to_string_lossy(),
+                    second.to_string_lossy(),
+                    name
+                ),
+                Error::DependencyCycle(cycle) =>
+                    format!("Dependency cycle between projects: {}", cycle.join(" -> ")),
                 Error::InstallTargetError(target, error) =>
                     format!("Error while installing {} ({})", target, error),
+                Error::CurrentDirectoryError(error) =>
+                    format!("Unable to get the current directory ({})", error),
+                Error::ChangeDirectoryError(directory, error) => format!(
+                    "Unable to change directory to {} ({})",
+                    directory.to_string_lossy(),
+                    error
+                ),
+                Error::WriteStateError(path, error) => format!(
+                    "Unable to write build state {} ({})",
+                    path.to_string_lossy(),
+                    error
+                ),
+                Error::WriteCompileCommandsError(path, error) =>
+                    format!("Unable to write {} ({})", path.to_string_lossy(), error),
+                Error::ReadCompileCommandsError(path, error) =>
+                    format!("Unable to read {} ({})", path.to_string_lossy(), error),
+                Error::ParseCompileCommandsError(path, error) =>
+                    format!("Unable to parse {} ({})", path.to_string_lossy(), error),
+                Error::SymlinkError(link, error) => format!(
+                    "Unable to create link {} ({})",
+                    link.to_string_lossy(),
+                    error
+                ),
             }
         )
     }
@@ -0,0 +1,79 @@
+use super::Language;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileClass {
+    Source(Language),
+    Ignore,
+}
+
+// Maps file extensions to the language used to compile them
+pub struct Extensions {
+    map: BTreeMap<String, FileClass>,
+}
+
+impl FileClass {
+    pub fn parse(name: &str) -> Result<Self, super::error::Error> {
+        match name {
+            "ignore" => Ok(FileClass::Ignore),
+            _ => Ok(FileClass::Source(Language::parse(name)?)),
+        }
+    }
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        let mut map = BTreeMap::new();
+        map.insert("asm".to_owned(), FileClass::Source(Language::Assembly));
+        map.insert("s".to_owned(), FileClass::Source(Language::Assembly));
+        map.insert("c".to_owned(), FileClass::Source(Language::C));
+        map.insert("cpp".to_owned(), FileClass::Source(Language::CPlusPlus));
+        map.insert("h".to_owned(), FileClass::Ignore);
+        map.insert("hpp".to_owned(), FileClass::Ignore);
+
+        Extensions { map }
+    }
+
+    pub fn set(&mut self, extension: String, class: FileClass) {
+        self.map.insert(extension, class);
+    }
+
+    pub fn classify(&self, path: &Path) -> Option<FileClass> {
+        let extension = path.extension()?.to_string_lossy();
+        self.map.get(extension.as_ref()).copied()
+    }
+
+    // Returns the extensions which can appear in a project using "languages"
+    pub fn recognised(&self, languages: &BTreeSet<Language>) -> Vec<String> {
+        self.map
+            .iter()
+            .filter(|(_, class)| match class {
+                FileClass::Source(language) => languages.contains(language),
+                FileClass::Ignore => true,
+            })
+            .map(|(extension, _)| format!(".{}", extension))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for FileClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileClass::Source(language) => write!(f, "{}", language),
+            FileClass::Ignore => write!(f, "Ignored"),
+        }
+    }
+}
+
+impl std::fmt::Display for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (extension, class) in &self.map {
+            writeln!(f, " - .{}: {}", extension, class)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use super::{Language, Toolchain};
+use std::path::PathBuf;
+
+// Flags added on top of the toolchain's defaults
+#[derive(Clone, Default)]
+pub struct Flags {
+    c: Vec<String>,
+    cpp: Vec<String>,
+    assembly: Vec<String>,
+    linker: Vec<String>,
+    defines: Vec<String>,
+    include_dirs: Vec<PathBuf>,
+}
+
+impl Flags {
+    pub fn new() -> Self {
+        Flags::default()
+    }
+
+    pub fn add_compiler_flags(&mut self, language: Language, mut flags: Vec<String>) {
+        match language {
+            Language::Assembly => self.assembly.append(&mut flags),
+            Language::C => self.c.append(&mut flags),
+            Language::CPlusPlus => self.cpp.append(&mut flags),
+        }
+    }
+
+    pub fn add_linker_flags(&mut self, mut flags: Vec<String>) {
+        self.linker.append(&mut flags);
+    }
+
+    pub fn add_defines(&mut self, mut defines: Vec<String>) {
+        self.defines.append(&mut defines);
+    }
+
+    pub fn add_include_dirs(&mut self, mut include_dirs: Vec<PathBuf>) {
+        self.include_dirs.append(&mut include_dirs);
+    }
+
+    fn compiler_flags(&self, language: Language) -> &[String] {
+        match language {
+            Language::Assembly => &self.assembly,
+            Language::C => &self.c,
+            Language::CPlusPlus => &self.cpp,
+        }
+    }
+
+    // Appends the flags to the commands of the toolchain
+    pub fn apply(&self, toolchain: &mut Toolchain) {
+        for language in [Language::Assembly, Language::C, Language::CPlusPlus] {
+            let compiler = toolchain.compiler_mut(language);
+
+            for include_dir in &self.include_dirs {
+                let mut include_dir = format!("{}", include_dir.to_string_lossy());
+
+                // nasm prepends include directories directly to the filename
+                if language == Language::Assembly && !include_dir.ends_with('/') {
+                    include_dir.push('/');
+                }
+
+                compiler.add_flag(format!("-I{}", include_dir));
+            }
+
+            for define in &self.defines {
+                compiler.add_flag(format!("-D{}", define));
+            }
+
+            for flag in self.compiler_flags(language) {
+                compiler.add_flag(flag.to_owned());
+            }
+        }
+
+        for flag in &self.linker {
+            toolchain.linker_mut().add_flag(flag.to_owned());
+        }
+    }
+}
+
+fn write_list(f: &mut std::fmt::Formatter<'_>, name: &str, list: &[String]) -> std::fmt::Result {
+    if !list.is_empty() {
+        writeln!(f, "{}: {}", name, list.join(" "))?;
+    }
+
+    Ok(())
+}
+
+impl std::fmt::Display for Flags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_list(f, "C Flags", &self.c)?;
+        write_list(f, "C++ Flags", &self.cpp)?;
+        write_list(f, "Assembly Flags", &self.assembly)?;
+        write_list(f, "Linker Flags", &self.linker)?;
+        write_list(f, "Defines", &self.defines)?;
+
+        if !self.include_dirs.is_empty() {
+            writeln!(f, "Include Directories:")?;
+            for include_dir in &self.include_dirs {
+                writeln!(f, " - {}", include_dir.to_string_lossy())?;
+            }
+        }
+
+        Ok(())
+    }
+}
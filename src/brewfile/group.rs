@@ -0,0 +1,133 @@
+use super::error::Error;
+use crate::arguments::Options;
+use std::{collections::HashMap, path::PathBuf};
+
+struct Project {
+    path: PathBuf,
+    name: Option<String>,
+    dependencies: Vec<usize>,
+    priority: usize,
+}
+
+impl Project {
+    fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.to_owned(),
+            None => format!("{}", self.path.to_string_lossy()),
+        }
+    }
+}
+
+// Orders the sub-projects so every project is brewed after the projects in
+// the group it depends on. Ties are broken by the "priority" list and then by
+// path so the order is the same on every run. Each directory is returned with
+// the name of its project.
+pub fn build_order(
+    directories: Vec<PathBuf>,
+    priority: &[PathBuf],
+    options: &Options,
+) -> Result<Vec<(PathBuf, Option<String>)>, Error> {
+    // Read the name and dependencies of every project
+    let mut projects: Vec<Project> = Vec::with_capacity(directories.len());
+    let mut declared_dependencies = Vec::with_capacity(directories.len());
+    let mut names = HashMap::new();
+    for path in directories {
+        let brewfile = match crate::parser::parse_brewfile(
+            &path.join(crate::config::BREWFILE_NAME),
+            options,
+        ) {
+            Ok(brewfile) => brewfile,
+            Err(error) => return Err(Error::SubBrewfileError(path, format!("{}", error))),
+        };
+
+        let name = brewfile.name().map(|name| name.to_owned());
+        if let Some(name) = &name {
+            if let Some(other) = names.insert(name.to_owned(), projects.len()) {
+                return Err(Error::ProjectNameConflict(
+                    name.to_owned(),
+                    projects[other].path.clone(),
+                    path,
+                ));
+            }
+        }
+
+        let priority = priority
+            .iter()
+            .position(|priority| {
+                priority == &path || path.file_name() == Some(priority.as_os_str())
+            })
+            .unwrap_or(usize::MAX);
+
+        declared_dependencies.push(brewfile.dependencies().to_vec());
+        projects.push(Project {
+            path,
+            name,
+            dependencies: Vec::new(),
+            priority,
+        });
+    }
+
+    // Only dependencies on projects in this group affect the order
+    for (project, dependencies) in projects.iter_mut().zip(declared_dependencies) {
+        for dependency in dependencies {
+            if let Some(index) = names.get(&dependency) {
+                project.dependencies.push(*index);
+            }
+        }
+    }
+
+    // Repeatedly brew the first ready project
+    let mut brewed = vec![false; projects.len()];
+    let mut order = Vec::with_capacity(projects.len());
+    while order.len() < projects.len() {
+        let next = (0..projects.len())
+            .filter(|&index| {
+                !brewed[index]
+                    && projects[index]
+                        .dependencies
+                        .iter()
+                        .all(|&dependency| brewed[dependency])
+            })
+            .min_by(|&a, &b| {
+                (projects[a].priority, &projects[a].path)
+                    .cmp(&(projects[b].priority, &projects[b].path))
+            });
+
+        match next {
+            Some(index) => {
+                brewed[index] = true;
+                order.push(index);
+            }
+            None => return Err(Error::DependencyCycle(find_cycle(&projects, &brewed))),
+        }
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|index| (projects[index].path.clone(), projects[index].name.clone()))
+        .collect())
+}
+
+// Follows unbrewed dependencies until a project repeats. Every unbrewed
+// project has an unbrewed dependency, so this always finds a cycle.
+fn find_cycle(projects: &[Project], brewed: &[bool]) -> Vec<String> {
+    let mut stack: Vec<usize> = Vec::new();
+    let mut current = brewed.iter().position(|brewed| !brewed).unwrap();
+    loop {
+        if let Some(start) = stack.iter().position(|&index| index == current) {
+            let mut cycle: Vec<String> = stack[start..]
+                .iter()
+                .map(|&index| projects[index].display_name())
+                .collect();
+            cycle.push(projects[current].display_name());
+            return cycle;
+        }
+
+        stack.push(current);
+        current = *projects[current]
+            .dependencies
+            .iter()
+            .find(|&&dependency| !brewed[dependency])
+            .unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Incremental 64-bit FNV-1a hash, used by --hash to tell whether an input
+// changed since the state file was written on this machine
+pub struct Hasher {
+    hash: u64,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Hasher {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+const SHA256_INITIAL: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA256_ROUNDS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// Incremental SHA-256, used for the keys and identities of the shared object
+// cache, which may be shared between machines and where a collision would
+// silently give the wrong object
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_length: usize,
+    length: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Sha256 {
+            state: SHA256_INITIAL,
+            block: [0; 64],
+            block_length: 0,
+            length: 0,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u64);
+        for byte in bytes {
+            self.block[self.block_length] = *byte;
+            self.block_length += 1;
+            if self.block_length == 64 {
+                self.compress();
+                self.block_length = 0;
+            }
+        }
+    }
+
+    // Returns the digest as lowercase hexadecimal
+    pub fn finish(mut self) -> String {
+        let bits = self.length.wrapping_mul(8);
+        self.write(&[0x80]);
+        while self.block_length != 56 {
+            self.write(&[0]);
+        }
+        self.write(&bits.to_be_bytes());
+
+        self.state
+            .iter()
+            .map(|word| format!("{:08x}", word))
+            .collect()
+    }
+
+    fn compress(&mut self) {
+        let mut schedule = [0u32; 64];
+        for (index, word) in self.block.chunks(4).enumerate() {
+            schedule[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for index in 16..64 {
+            let s0 = schedule[index - 15].rotate_right(7)
+                ^ schedule[index - 15].rotate_right(18)
+                ^ (schedule[index - 15] >> 3);
+            let s1 = schedule[index - 2].rotate_right(17)
+                ^ schedule[index - 2].rotate_right(19)
+                ^ (schedule[index - 2] >> 10);
+            schedule[index] = schedule[index - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[index - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for index in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temporary1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(SHA256_ROUNDS[index])
+                .wrapping_add(schedule[index]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temporary2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temporary1);
+            d = c;
+            c = b;
+            b = a;
+            a = temporary1.wrapping_add(temporary2);
+        }
+
+        for (word, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+}
+
+// Returns the hash of the contents of "path"
+pub fn file(path: &Path) -> Option<u64> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return None,
+    };
+
+    let mut hasher = Hasher::new();
+    let mut buffer = [0; 8192];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => hasher.write(&buffer[..length]),
+            Err(_) => return None,
+        }
+    }
+
+    Some(hasher.finish())
+}
+
+// Returns the hash of every file, or None if any of them can't be read
+pub fn files<'a, I: IntoIterator<Item = &'a Path>>(paths: I) -> Option<BTreeMap<PathBuf, u64>> {
+    let mut hashes = BTreeMap::new();
+    for path in paths {
+        hashes.insert(path.to_owned(), file(path)?);
+    }
+
+    Some(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha256;
+
+    fn sha256(bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    #[test]
+    fn short_messages() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    // 55 bytes is the longest message whose padding fits in one block, 56
+    // needs a second block and 64 fills a block before any padding
+    #[test]
+    fn padding_boundaries() {
+        let expected = [
+            (
+                55,
+                "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+            ),
+            (
+                56,
+                "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+            ),
+            (
+                63,
+                "7d3e74a05d7db15bce4ad9ec0658ea98e3f06eeecf16b4c6fff2da457ddc2f34",
+            ),
+            (
+                64,
+                "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+            ),
+            (
+                65,
+                "635361c48bb9eab14198e76ea8ab7f1a41685d6ad62aa9146d301d4f17eb0ae0",
+            ),
+            (
+                1000,
+                "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3",
+            ),
+        ];
+
+        for (length, digest) in expected {
+            assert_eq!(sha256(&vec![b'a'; length]), digest, "{} bytes", length);
+        }
+    }
+
+    #[test]
+    fn split_writes() {
+        let message = vec![b'a'; 1000];
+        for split in [0, 1, 55, 56, 64, 999] {
+            let mut hasher = Sha256::new();
+            hasher.write(&message[..split]);
+            hasher.write(&message[split..]);
+            assert_eq!(hasher.finish(), sha256(&message), "split at {}", split);
+        }
+    }
+}
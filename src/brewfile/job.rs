@@ -0,0 +1,219 @@
+use super::{
+    cache::Cache,
+    dependency_file,
+    error::Error,
+    state::{self, State},
+};
+use crate::arguments::Options;
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// A single compile or assemble step
+pub struct Job {
+    source: PathBuf,
+    object: PathBuf,
+    action: &'static str,
+    compiler: &'static str,
+    program: String,
+    arguments: Vec<String>,
+    // The arguments which affect the output, excluding input and output paths
+    flags: Vec<String>,
+}
+
+impl Job {
+    pub fn new(
+        source: &Path,
+        object: &Path,
+        action: &'static str,
+        compiler: &'static str,
+        program: &str,
+    ) -> Self {
+        Job {
+            source: source.to_owned(),
+            object: object.to_owned(),
+            action,
+            compiler,
+            program: program.to_owned(),
+            arguments: Vec::new(),
+            flags: Vec::new(),
+        }
+    }
+
+    pub fn arg<S: AsRef<str>>(&mut self, argument: S) {
+        self.arguments.push(argument.as_ref().to_owned());
+    }
+
+    pub fn path_arg(&mut self, path: &Path) {
+        self.arg(path.to_string_lossy());
+    }
+
+    // Adds an argument which affects the output
+    pub fn flag<S: AsRef<str>>(&mut self, flag: S) {
+        self.arg(&flag);
+        self.flags.push(flag.as_ref().to_owned());
+    }
+
+    pub fn flags<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, flags: I) {
+        for flag in flags {
+            self.flag(flag);
+        }
+    }
+
+    pub fn get_flags(&self) -> &[String] {
+        &self.flags
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn object(&self) -> &Path {
+        &self.object
+    }
+
+    // Returns the program followed by its arguments
+    pub fn command_line(&self) -> Vec<String> {
+        state::command_line(&self.command())
+    }
+
+    // Returns the source followed by the files it included when it was last
+    // compiled, using the compiler generated dependency list
+    fn inputs(&self) -> Option<Vec<PathBuf>> {
+        let mut inputs = dependency_file::parse(&dependency_file::path(&self.object))?;
+        inputs.insert(0, self.source.clone());
+        Some(inputs)
+    }
+
+    // Returns true if the object is out of date or was last built with a
+    // different command
+    pub fn needs_compile(&self, state: &State, options: &Options) -> bool {
+        let inputs = match self.inputs() {
+            Some(inputs) => inputs,
+            None => return true,
+        };
+
+        let hashes = state::input_hashes(&inputs, options);
+        !state::is_up_to_date(
+            &self.object,
+            &self.command_line(),
+            &inputs,
+            &hashes,
+            state,
+            options,
+        )
+    }
+
+    // Returns the hashes of the inputs after a successful run when hashing
+    pub fn input_hashes(&self, options: &Options) -> Option<BTreeMap<PathBuf, u64>> {
+        match options.hash() {
+            true => state::input_hashes(&self.inputs()?, options),
+            false => None,
+        }
+    }
+
+    // Records how the object was built so later runs can tell if it changed
+    pub fn record(&self, state: &mut State, hashes: Option<BTreeMap<PathBuf, u64>>) {
+        state::record(&self.object, self.command_line(), hashes, state);
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.arguments);
+        command
+    }
+
+    // Returns the command which writes the preprocessed source to stdout
+    pub fn preprocess_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.flags);
+        command.arg("-E");
+        command.arg(&self.source);
+        command
+    }
+
+    fn print_message(&self, action: &str, options: &Options) {
+        if !options.quiet() {
+            println!(
+                "{} {} to {} . . .",
+                action,
+                self.source.to_string_lossy(),
+                self.object.to_string_lossy()
+            );
+        }
+    }
+
+    // Restores the object from the cache if possible, otherwise calls
+    // "compile" and adds its output to the cache
+    fn run_cached<F: FnOnce() -> Result<(), Error>>(
+        &self,
+        cache: Option<&Cache>,
+        options: &Options,
+        compile: F,
+    ) -> Result<(), Error> {
+        let key = match cache {
+            Some(cache) => cache.key(self).map(|key| (cache, key)),
+            None => None,
+        };
+
+        if let Some((cache, key)) = &key {
+            if cache.restore(self, key) {
+                self.print_message("Restoring", options);
+                return Ok(());
+            }
+        }
+
+        self.print_message(self.action, options);
+        compile()?;
+
+        if let Some((cache, key)) = &key {
+            cache.store(self, key);
+        }
+
+        Ok(())
+    }
+
+    // Runs the job with the compiler writing directly to the terminal
+    pub fn run(&self, cache: Option<&Cache>, options: &Options) -> Result<(), Error> {
+        self.run_cached(cache, options, || match self.command().status() {
+            Ok(status) => match status.success() {
+                true => Ok(()),
+                false => Err(Error::CompileError(self.source.clone())),
+            },
+            Err(error) => Err(Error::RunCompilerError(self.compiler, error)),
+        })
+    }
+
+    // Runs the job capturing the compiler output so that concurrent jobs
+    // do not interleave their diagnostics
+    pub fn run_captured(&self, cache: Option<&Cache>, options: &Options) -> Result<(), Error> {
+        self.run_cached(cache, options, || self.compile_captured())
+    }
+
+    fn compile_captured(&self) -> Result<(), Error> {
+        let output = match self.command().output() {
+            Ok(output) => output,
+            Err(error) => return Err(Error::RunCompilerError(self.compiler, error)),
+        };
+
+        if !output.stdout.is_empty() || !output.stderr.is_empty() {
+            let stdout = std::io::stdout();
+            let mut stdout = stdout.lock();
+            stdout.write_all(&output.stdout).ok();
+            stdout.flush().ok();
+            std::io::stderr().write_all(&output.stderr).ok();
+        }
+
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(Error::CompileError(self.source.clone())),
+        }
+    }
+}
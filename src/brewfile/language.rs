@@ -1,56 +1,14 @@
-use super::error::Error;
+use super::{cache::Cache, dependency_file, error::Error, job::Job, state::State, Toolchain};
 use crate::arguments::Options;
-use std::{path::Path, process::Command};
+use std::path::Path;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Language {
     Assembly,
     C,
     CPlusPlus,
 }
 
-pub enum CompileStatus {
-    None,
-    Ignore,
-    Complete,
-}
-
-fn get_extension(path: &Path) -> String {
-    format!(
-        "{}",
-        match path.extension() {
-            Some(str) => str,
-            None => return String::new(),
-        }
-        .to_string_lossy()
-    )
-}
-
-// Returns true if the file should be compiled
-fn check_date(source: &Path, object: &Path) -> bool {
-    if !object.exists() {
-        return true;
-    }
-
-    let source_time = match source.metadata() {
-        Ok(meta) => match meta.modified() {
-            Ok(time) => time,
-            Err(_) => return true,
-        },
-        Err(_) => return true,
-    };
-
-    let object_time = match object.metadata() {
-        Ok(meta) => match meta.modified() {
-            Ok(time) => time,
-            Err(_) => return true,
-        },
-        Err(_) => return true,
-    };
-
-    return source_time > object_time;
-}
-
 impl Language {
     pub fn parse(name: &str) -> Result<Self, Error> {
         Ok(match name {
@@ -61,141 +19,115 @@ impl Language {
         })
     }
 
-    pub fn compile(
+    pub fn job(
         &self,
         source_path: &Path,
         destination_path: &Path,
+        toolchain: &Toolchain,
         options: &Options,
-    ) -> Result<CompileStatus, Error> {
+    ) -> Job {
         match self {
-            Language::Assembly => Self::compile_assembly(source_path, destination_path, options),
-            Language::C => Self::compile_c(source_path, destination_path, options),
-            Language::CPlusPlus => Self::compile_cpp(source_path, destination_path, options),
+            Language::Assembly => self.assembly_job(source_path, destination_path, toolchain),
+            Language::C => self.c_job(source_path, destination_path, toolchain, options),
+            Language::CPlusPlus => self.cpp_job(source_path, destination_path, toolchain, options),
         }
     }
 
-    fn compile_c(
+    // Compiles a single file immediately if it is out of date or was last
+    // built with a different command
+    pub fn compile(
+        &self,
         source_path: &Path,
         destination_path: &Path,
+        toolchain: &Toolchain,
+        state: &mut State,
+        cache: Option<&Cache>,
         options: &Options,
-    ) -> Result<CompileStatus, Error> {
-        // Verify extension
-        match get_extension(source_path).as_str() {
-            "c" => {}
-            "h" => return Ok(CompileStatus::Ignore),
-            _ => return Ok(CompileStatus::None),
-        }
-
-        // Verify time
-        if !check_date(source_path, destination_path) {
-            return Ok(CompileStatus::Complete);
+    ) -> Result<(), Error> {
+        let job = self.job(source_path, destination_path, toolchain, options);
+        if job.needs_compile(state, options) {
+            job.run(cache, options)?;
+            job.record(state, job.input_hashes(options));
         }
 
-        // Compile file
-        if !options.quiet() {
-            println!(
-                "Compiling {} to {} . . .",
-                source_path.to_string_lossy(),
-                destination_path.to_string_lossy()
-            );
-        }
-
-        let mut command = Command::new(crate::config::C_COMPILER);
-        command.args(crate::config::C_COMPILER_FLAGS);
-        command.arg("-o");
-        command.arg(destination_path);
-        command.arg(source_path);
-        command.arg(format!("--sysroot={}", options.sysroot().to_string_lossy()));
-
-        match command.status() {
-            Ok(status) => match status.success() {
-                true => Ok(CompileStatus::Complete),
-                false => Err(Error::CompileError(source_path.to_owned())),
-            },
-            Err(error) => Err(Error::RunCompilerError("c", error)),
-        }
+        Ok(())
     }
 
-    fn compile_cpp(
+    fn c_job(
+        &self,
         source_path: &Path,
         destination_path: &Path,
+        toolchain: &Toolchain,
         options: &Options,
-    ) -> Result<CompileStatus, Error> {
-        // Verify extension
-        match get_extension(source_path).as_str() {
-            "cpp" => {}
-            "h" | "hpp" => return Ok(CompileStatus::Ignore),
-            _ => return Ok(CompileStatus::None),
-        }
-
-        // Verify time
-        if !check_date(source_path, destination_path) {
-            return Ok(CompileStatus::Complete);
-        }
-
-        // Compile file
-        if !options.quiet() {
-            println!(
-                "Compiling {} to {} . . .",
-                source_path.to_string_lossy(),
-                destination_path.to_string_lossy()
-            );
-        }
-
-        let mut command = Command::new(crate::config::CPP_COMPILER);
-        command.args(crate::config::CPP_COMPILER_FLAGS);
-        command.arg("-o");
-        command.arg(destination_path);
-        command.arg(source_path);
-        command.arg(format!("--sysroot={}", options.sysroot().to_string_lossy()));
-
-        match command.status() {
-            Ok(status) => match status.success() {
-                true => Ok(CompileStatus::Complete),
-                false => Err(Error::CompileError(source_path.to_owned())),
-            },
-            Err(error) => Err(Error::RunCompilerError("c++", error)),
-        }
+    ) -> Job {
+        let mut job = Job::new(
+            source_path,
+            destination_path,
+            "Compiling",
+            "c",
+            toolchain.compiler(*self).executable(),
+        );
+        job.flags(toolchain.compiler(*self).flags());
+        job.arg("-c");
+        job.arg("-MD");
+        job.arg("-MF");
+        job.path_arg(&dependency_file::path(destination_path));
+        job.arg("-o");
+        job.path_arg(destination_path);
+        job.path_arg(source_path);
+        job.flag(format!("--sysroot={}", options.sysroot().to_string_lossy()));
+
+        job
     }
 
-    fn compile_assembly(
+    fn cpp_job(
+        &self,
         source_path: &Path,
         destination_path: &Path,
+        toolchain: &Toolchain,
         options: &Options,
-    ) -> Result<CompileStatus, Error> {
-        // Verify extension
-        match get_extension(source_path).as_str() {
-            "asm" | "s" => {}
-            _ => return Ok(CompileStatus::None),
-        }
-
-        // Verify time
-        if !check_date(source_path, destination_path) {
-            return Ok(CompileStatus::Complete);
-        }
-
-        // Assemble file
-        if !options.quiet() {
-            println!(
-                "Assembling {} to {} . . .",
-                source_path.to_string_lossy(),
-                destination_path.to_string_lossy()
-            );
-        }
-
-        let mut command = Command::new(crate::config::ASSEMBLER);
-        command.args(crate::config::ASSEMBLER_FLAGS);
-        command.arg("-o");
-        command.arg(destination_path);
-        command.arg(source_path);
+    ) -> Job {
+        let mut job = Job::new(
+            source_path,
+            destination_path,
+            "Compiling",
+            "c++",
+            toolchain.compiler(*self).executable(),
+        );
+        job.flags(toolchain.compiler(*self).flags());
+        job.arg("-c");
+        job.arg("-MD");
+        job.arg("-MF");
+        job.path_arg(&dependency_file::path(destination_path));
+        job.arg("-o");
+        job.path_arg(destination_path);
+        job.path_arg(source_path);
+        job.flag(format!("--sysroot={}", options.sysroot().to_string_lossy()));
+
+        job
+    }
 
-        match command.status() {
-            Ok(status) => match status.success() {
-                true => Ok(CompileStatus::Complete),
-                false => Err(Error::CompileError(source_path.to_owned())),
-            },
-            Err(error) => Err(Error::RunCompilerError("assembly", error)),
-        }
+    fn assembly_job(
+        &self,
+        source_path: &Path,
+        destination_path: &Path,
+        toolchain: &Toolchain,
+    ) -> Job {
+        let mut job = Job::new(
+            source_path,
+            destination_path,
+            "Assembling",
+            "assembly",
+            toolchain.compiler(*self).executable(),
+        );
+        job.flags(toolchain.compiler(*self).flags());
+        job.arg("-MD");
+        job.path_arg(&dependency_file::path(destination_path));
+        job.arg("-o");
+        job.path_arg(destination_path);
+        job.path_arg(source_path);
+
+        job
     }
 }
 
@@ -1,16 +1,41 @@
 use crate::arguments::Options;
-use std::{collections::HashSet, path::PathBuf, process::Command};
-
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+mod cache;
+mod compile_commands;
+mod dependency_file;
 pub mod error;
+mod extensions;
+mod flags;
+mod group;
+mod hash;
+mod job;
 mod language;
 mod object;
-
+mod profile;
+mod scheduler;
+mod state;
+mod timestamp;
+mod toolchain;
+
+use cache::Cache;
+pub use extensions::FileClass;
+pub use flags::Flags;
 pub use language::Language;
 pub use object::Object;
+use state::State;
+pub use toolchain::Toolchain;
 
 pub enum BrewType {
     Executable,
     Library,
+    SharedLibrary,
+    Binary,
     Group,
     None,
 }
@@ -18,10 +43,27 @@ pub enum BrewType {
 pub struct Brewfile {
     name: Option<String>,
     brew_type: BrewType,
-    languages: HashSet<Language>,
-    dependencies: HashSet<String>,
+    languages: BTreeSet<Language>,
+    extensions: extensions::Extensions,
+    dependencies: Vec<String>,
     objects: Vec<Object>,
     priority: Vec<PathBuf>,
+    toolchain: Option<PathBuf>,
+    flags: Flags,
+    profiles: BTreeMap<String, Flags>,
+    version: Option<String>,
+    linker_script: Option<PathBuf>,
+    entry: Option<String>,
+    output: Option<String>,
+    install_dir: Option<PathBuf>,
+    build_dir: Option<PathBuf>,
+}
+
+// Drops "." components so paths built from "./obj" and "obj" compare equal
+fn normalise(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != std::path::Component::CurDir)
+        .collect()
 }
 
 impl Brewfile {
@@ -29,10 +71,20 @@ impl Brewfile {
         Brewfile {
             name: None,
             brew_type: BrewType::None,
-            dependencies: HashSet::new(),
-            languages: HashSet::new(),
+            dependencies: Vec::new(),
+            languages: BTreeSet::new(),
+            extensions: extensions::Extensions::new(),
             objects: Vec::new(),
             priority: Vec::new(),
+            toolchain: None,
+            flags: Flags::new(),
+            profiles: BTreeMap::new(),
+            version: None,
+            linker_script: None,
+            entry: None,
+            output: None,
+            install_dir: None,
+            build_dir: None,
         }
     }
 
@@ -58,14 +110,127 @@ impl Brewfile {
         }
     }
 
+    pub fn set_extension(&mut self, extension: String, class: FileClass) {
+        self.extensions.set(extension, class);
+    }
+
     pub fn add_dependency(&mut self, dependency: String) -> Result<(), error::Error> {
-        if !self.dependencies.insert(dependency.clone()) {
+        if self.dependencies.contains(&dependency) {
             Err(error::Error::DependencyDefinedTwice(dependency))
         } else {
+            self.dependencies.push(dependency);
             Ok(())
         }
     }
 
+    pub fn set_version(&mut self, version: String) -> Result<(), error::Error> {
+        match self.version {
+            Some(_) => Err(error::Error::VersionDefinedTwice),
+            None => {
+                self.version = Some(version);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_linker_script(&mut self, linker_script: PathBuf) -> Result<(), error::Error> {
+        match self.linker_script {
+            Some(_) => Err(error::Error::LinkerScriptDefinedTwice),
+            None => {
+                self.linker_script = Some(linker_script);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_entry(&mut self, entry: String) -> Result<(), error::Error> {
+        match self.entry {
+            Some(_) => Err(error::Error::EntryDefinedTwice),
+            None => {
+                self.entry = Some(entry);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_output(&mut self, output: String) -> Result<(), error::Error> {
+        match self.output {
+            Some(_) => Err(error::Error::OutputDefinedTwice),
+            None => {
+                self.output = Some(output);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_install_dir(&mut self, install_dir: PathBuf) -> Result<(), error::Error> {
+        match self.install_dir {
+            Some(_) => Err(error::Error::InstallDirDefinedTwice),
+            None => {
+                self.install_dir = Some(install_dir);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_build_dir(&mut self, build_dir: PathBuf) -> Result<(), error::Error> {
+        match self.build_dir {
+            Some(_) => Err(error::Error::BuildDirDefinedTwice),
+            None => {
+                self.build_dir = Some(build_dir);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_toolchain(&mut self, toolchain: PathBuf) -> Result<(), error::Error> {
+        match self.toolchain {
+            Some(_) => Err(error::Error::ToolchainDefinedTwice),
+            None => {
+                self.toolchain = Some(toolchain);
+                Ok(())
+            }
+        }
+    }
+
+    // Checks keys which are only invalid in combination with others
+    pub fn validate(&self) -> Result<(), error::Error> {
+        match self.brew_type {
+            BrewType::SharedLibrary | BrewType::None => {}
+            _ => {
+                if self.version.is_some() {
+                    return Err(error::Error::VersionNotSupported(format!(
+                        "{}",
+                        self.brew_type
+                    )));
+                }
+            }
+        }
+
+        match self.brew_type {
+            BrewType::Executable | BrewType::Binary | BrewType::Group | BrewType::None => Ok(()),
+            _ => match self.output {
+                Some(_) => Err(error::Error::OutputNotSupported(format!(
+                    "{}",
+                    self.brew_type
+                ))),
+                None => Ok(()),
+            },
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    pub fn toolchain(&self) -> Option<&Path> {
+        self.toolchain.as_deref()
+    }
+
     pub fn add_object(&mut self, object: Object) {
         self.objects.push(object);
     }
@@ -74,6 +239,41 @@ impl Brewfile {
         self.priority.push(priority);
     }
 
+    pub fn flags_mut(&mut self) -> &mut Flags {
+        &mut self.flags
+    }
+
+    pub fn profile_flags_mut(&mut self, profile: String) -> &mut Flags {
+        self.profiles.entry(profile).or_default()
+    }
+
+    // Adds the selected profile's and the project's flags to the toolchain.
+    // Flags declared in the brewfile come after the built-in ones so they can
+    // override them.
+    fn apply_flags(
+        &self,
+        toolchain: &mut Toolchain,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let builtin = profile::builtin(options.profile());
+        let declared = self.profiles.get(options.profile());
+        if builtin.is_none() && declared.is_none() {
+            return Err(error::Error::UnknownProfile(options.profile().to_owned()));
+        }
+
+        if let Some(flags) = builtin {
+            flags.apply(toolchain);
+        }
+
+        self.flags.apply(toolchain);
+
+        if let Some(flags) = declared {
+            flags.apply(toolchain);
+        }
+
+        Ok(())
+    }
+
     fn install_include_directory(
         source_path: PathBuf,
         destination_path: PathBuf,
@@ -123,6 +323,8 @@ impl Brewfile {
         &self,
         source_path: PathBuf,
         destination_path: PathBuf,
+        jobs: &mut Vec<job::Job>,
+        toolchain: &Toolchain,
         options: &Options,
     ) -> Result<Vec<PathBuf>, error::Error> {
         // Create output directory
@@ -142,7 +344,7 @@ impl Brewfile {
             Err(error) => return Err(error::Error::DirectoryReadError(source_path, error)),
         };
 
-        // Compile sub-directories and files
+        // Collect jobs for sub-directories and files
         let mut objects = Vec::new();
         for entry in source_directory {
             let entry = match entry {
@@ -156,30 +358,38 @@ impl Brewfile {
                 continue;
             }
 
-            let mut object_path = destination_path.join(path.file_name().unwrap());
+            let object_path = destination_path.join(path.file_name().unwrap());
             if path.is_dir() {
-                let mut sub_objects = self.compile_directory(path, object_path, options)?;
+                let mut sub_objects =
+                    self.compile_directory(path, object_path, jobs, toolchain, options)?;
                 objects.append(&mut sub_objects);
             } else {
-                object_path.set_extension("o");
-                let mut compiled = false;
-                'language_loop: for language in &self.languages {
-                    match language.compile(&path, &object_path, options)? {
-                        language::CompileStatus::Complete => {
-                            compiled = true;
-                            objects.push(object_path);
-                            break 'language_loop;
+                // Keep the source extension so "boot.c" and "boot.asm" do not
+                // both become "boot.o"
+                let mut object_name = object_path.into_os_string();
+                object_name.push(".o");
+                let object_path = PathBuf::from(object_name);
+
+                match self.extensions.classify(&path) {
+                    Some(FileClass::Source(language)) if self.languages.contains(&language) => {
+                        if options.verbose() {
+                            println!("{} is {}", path.to_string_lossy(), language);
                         }
-                        language::CompileStatus::Ignore => {
-                            compiled = true;
-                            break 'language_loop;
+
+                        jobs.push(language.job(&path, &object_path, toolchain, options));
+                        objects.push(object_path);
+                    }
+                    Some(FileClass::Ignore) => {
+                        if options.verbose() {
+                            println!("{} is ignored", path.to_string_lossy());
                         }
-                        _ => {}
                     }
-                }
-
-                if !compiled {
-                    return Err(error::Error::UncompiledFile(path));
+                    _ => {
+                        return Err(error::Error::UncompiledFile(
+                            path,
+                            self.extensions.recognised(&self.languages),
+                        ))
+                    }
                 }
             }
         }
@@ -188,73 +398,555 @@ impl Brewfile {
     }
 
     // Compile all objects in sources folder and return a list of those objects
-    fn compile_source_directory(&self, options: &Options) -> Result<Vec<PathBuf>, error::Error> {
+    fn compile_source_directory(
+        &self,
+        toolchain: &Toolchain,
+        state: &mut State,
+        cache: Option<&Cache>,
+        options: &Options,
+    ) -> Result<Vec<PathBuf>, error::Error> {
         if options.verbose() {
             println!()
         }
 
-        self.compile_directory(
+        let mut jobs = Vec::new();
+        let objects = self.compile_directory(
             PathBuf::from(crate::config::SOURCES_PATH),
-            PathBuf::from(crate::config::OBJECTS_PATH),
+            self.objects_path(options),
+            &mut jobs,
+            toolchain,
             options,
-        )
+        )?;
+
+        self.prune_objects(&objects, state, options)?;
+
+        // Rebuild objects which are out of date or were built with a
+        // different command
+        jobs.retain(|job| job.needs_compile(state, options));
+
+        // Record the jobs which succeed, even if another fails
+        let shared_state = Mutex::new(&mut *state);
+        let result = scheduler::run(jobs, cache, options, &|job| {
+            let hashes = job.input_hashes(options);
+            job.record(&mut shared_state.lock().unwrap(), hashes);
+        });
+        state.save()?;
+
+        result?;
+        Ok(objects)
     }
 
-    fn link_executable(
+    // Removes files under the objects directory which no longer correspond
+    // to a source, along with any directories left empty
+    fn prune_objects(
         &self,
-        objects: Vec<PathBuf>,
+        objects: &[PathBuf],
+        state: &mut State,
         options: &Options,
     ) -> Result<(), error::Error> {
-        let output = match &self.name {
-            Some(name) => format!("{}.app", name),
-            None => return Err(error::Error::NoName),
+        let mut keep = BTreeSet::new();
+        for object in objects {
+            keep.insert(dependency_file::path(object));
+            keep.insert(object.clone());
+        }
+        for object in &self.objects {
+            keep.insert(dependency_file::path(object.output()));
+            keep.insert(object.output().to_owned());
+        }
+        if let BrewType::Binary = self.brew_type {
+            keep.insert(self.binary_image(options)?);
+        }
+
+        let objects_path = self.objects_path(options);
+        keep.insert(objects_path.join(crate::config::STATE_NAME));
+        let keep: BTreeSet<PathBuf> = keep.iter().map(|path| normalise(path)).collect();
+
+        let mut pruned = Vec::new();
+        Self::prune_directory(
+            &objects_path,
+            Path::new(crate::config::SOURCES_PATH),
+            &keep,
+            &mut pruned,
+        )?;
+
+        for path in &pruned {
+            state.remove(path);
+        }
+
+        if options.verbose() && !pruned.is_empty() {
+            println!("Pruned:");
+            for path in &pruned {
+                println!(" - {}", path.to_string_lossy());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns true if the directory is empty after pruning
+    fn prune_directory(
+        directory: &Path,
+        source_directory: &Path,
+        keep: &BTreeSet<PathBuf>,
+        pruned: &mut Vec<PathBuf>,
+    ) -> Result<bool, error::Error> {
+        let entries = match std::fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) => {
+                return Err(error::Error::DirectoryReadError(
+                    directory.to_owned(),
+                    error,
+                ))
+            }
         };
 
-        let mut command = Command::new(crate::config::LINKER);
-        command.args(crate::config::LINKER_FLAGS);
+        let mut empty = true;
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    return Err(error::Error::DirectoryReadError(
+                        directory.to_owned(),
+                        error,
+                    ))
+                }
+            };
+
+            let path = entry.path();
+            if path.is_dir() {
+                let source_directory = source_directory.join(entry.file_name());
+                let now_empty = Self::prune_directory(&path, &source_directory, keep, pruned)?;
+
+                // Keep empty directories mirroring a source directory, as
+                // they would only be created again by the next build
+                if now_empty && !source_directory.is_dir() {
+                    match std::fs::remove_dir(&path) {
+                        Ok(()) => pruned.push(path),
+                        Err(error) => return Err(error::Error::RemoveDirectoryError(path, error)),
+                    }
+                } else {
+                    empty = false;
+                }
+            } else if keep.contains(&normalise(&path)) {
+                empty = false;
+            } else {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => pruned.push(path),
+                    Err(error) => {
+                        return Err(error::Error::RemoveTargetError(
+                            format!("{}", path.to_string_lossy()),
+                            error,
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(empty)
+    }
+
+    // Writes the command for every source and object without compiling
+    fn write_compile_commands(
+        &self,
+        toolchain: &Toolchain,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let mut jobs = Vec::new();
+        self.compile_directory(
+            PathBuf::from(crate::config::SOURCES_PATH),
+            self.objects_path(options),
+            &mut jobs,
+            toolchain,
+            options,
+        )?;
+
+        for object in &self.objects {
+            jobs.push(object.job(toolchain, options));
+        }
+
+        if !options.quiet() {
+            println!("Writing {} . . .", crate::config::COMPILE_COMMANDS_NAME);
+        }
+
+        compile_commands::write(Path::new(crate::config::COMPILE_COMMANDS_NAME), &jobs)
+    }
+
+    // Finds the library for a dependency, preferring the output of a project
+    // in the same group over the installed copy and a static library over a
+    // shared one
+    fn resolve_dependency(
+        &self,
+        dependency: &str,
+        options: &Options,
+    ) -> Result<PathBuf, error::Error> {
+        let mut directories = Vec::new();
+        if let Some(directory) = options.sibling(dependency) {
+            directories.push(directory.to_owned());
+        }
+
+        let prefix = options.prefix();
+        directories.push(
+            options
+                .sysroot()
+                .join(prefix.strip_prefix("/").unwrap_or(prefix))
+                .join("lib"),
+        );
+
+        let mut candidates = Vec::new();
+        for directory in directories {
+            candidates.push(directory.join(format!("lib{}.a", dependency)));
+            candidates.push(directory.join(format!("lib{}.so", dependency)));
+        }
+
+        for candidate in &candidates {
+            if candidate.exists() {
+                return Ok(candidate.to_owned());
+            }
+        }
+
+        Err(error::Error::DependencyNotFound(
+            dependency.to_owned(),
+            candidates,
+        ))
+    }
+
+    // Resolves the dependencies in the order they are declared
+    fn resolve_dependencies(&self, options: &Options) -> Result<Vec<PathBuf>, error::Error> {
+        let mut libraries = Vec::with_capacity(self.dependencies.len());
+        for dependency in &self.dependencies {
+            libraries.push(self.resolve_dependency(dependency, options)?);
+        }
+        Ok(libraries)
+    }
+
+    // Returns the directory objects, targets and build state are written to,
+    // with the command line taking precedence over the brewfile
+    fn build_directory<'a>(&'a self, options: &'a Options) -> Option<&'a Path> {
+        match options.build_dir() {
+            Some(build_dir) => Some(build_dir),
+            None => self.build_dir.as_deref(),
+        }
+    }
+
+    // Returns "path" moved into the build directory, if there is one
+    fn build_path<P: AsRef<Path>>(&self, path: P, options: &Options) -> PathBuf {
+        let path = path.as_ref();
+        match self.build_directory(options) {
+            Some(directory) => directory.join(path.strip_prefix(".").unwrap_or(path)),
+            None => path.to_owned(),
+        }
+    }
+
+    fn objects_path(&self, options: &Options) -> PathBuf {
+        self.build_path(crate::config::OBJECTS_PATH, options)
+            .join(options.profile())
+    }
+
+    // Builds a linker command using the toolchain, the linker script and
+    // entry point from the brewfile, and the resolved dependencies
+    fn linker_command(
+        &self,
+        output: &Path,
+        objects: &[PathBuf],
+        toolchain: &Toolchain,
+        options: &Options,
+    ) -> Result<Command, error::Error> {
+        let libraries = self.resolve_dependencies(options)?;
+        if options.verbose() && !libraries.is_empty() {
+            println!("Libraries to link:");
+            for library in &libraries {
+                println!(" - {}", library.to_string_lossy());
+            }
+        }
+
+        let mut command = Command::new(toolchain.linker().executable());
+        command.args(toolchain.linker().flags());
+        if let Some(linker_script) = &self.linker_script {
+            command.arg(format!("-Wl,-T,{}", linker_script.to_string_lossy()));
+        }
+        if let Some(entry) = &self.entry {
+            command.arg(format!("-Wl,-e,{}", entry));
+        }
         command.arg("-o");
-        command.arg(&output);
+        command.arg(output);
         command.args(objects);
+        command.args(libraries);
         command.arg(format!("--sysroot={}", options.sysroot().to_string_lossy()));
 
+        Ok(command)
+    }
+
+    // Returns every file the link step reads
+    fn link_inputs(
+        &self,
+        objects: &[PathBuf],
+        options: &Options,
+    ) -> Result<Vec<PathBuf>, error::Error> {
+        let mut inputs = objects.to_vec();
+
+        match self.brew_type {
+            BrewType::Library => {}
+            _ => inputs.append(&mut self.resolve_dependencies(options)?),
+        }
+
+        if let Some(linker_script) = &self.linker_script {
+            inputs.push(linker_script.clone());
+        }
+
+        Ok(inputs)
+    }
+
+    // Runs "command" to make "output" unless it is newer than "inputs" and
+    // was last made with the same command. "errors" are reported when the
+    // command fails or cannot be run.
+    fn link(
+        mut command: Command,
+        output: &Path,
+        inputs: &[PathBuf],
+        message: &str,
+        errors: (error::Error, fn(std::io::Error) -> error::Error),
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let command_line = state::command_line(&command);
+        let hashes = state::input_hashes(inputs, options);
+
+        if state::is_up_to_date(output, &command_line, inputs, &hashes, state, options) {
+            if !options.quiet() {
+                println!("{} is up to date", output.to_string_lossy());
+            }
+            return Ok(());
+        }
+
         if !options.quiet() {
-            println!("Linking {} . . .", output);
+            println!("{} . . .", message);
         }
 
+        // The archiver adds to an existing archive and never drops members,
+        // so start from nothing to leave out objects which were removed
+        if output.exists() {
+            if let Err(error) = std::fs::remove_file(output) {
+                return Err(error::Error::RemoveTargetError(
+                    format!("{}", output.to_string_lossy()),
+                    error,
+                ));
+            }
+        }
+
+        let (error, run_error) = errors;
         match command.status() {
             Ok(status) => match status.success() {
-                true => Ok(()),
-                false => Err(error::Error::LinkerError),
+                true => {}
+                false => return Err(error),
             },
-            Err(error) => Err(error::Error::RunLinkerError(error)),
+            Err(error) => return Err(run_error(error)),
         }
+
+        state::record(output, command_line, hashes, state);
+        Ok(())
     }
 
-    fn link_library(&self, objects: Vec<PathBuf>, options: &Options) -> Result<(), error::Error> {
-        let output = match &self.name {
-            Some(name) => format!("lib{}.a", name),
+    // Links "objects" and the project's dependencies into "output"
+    fn link_objects(
+        &self,
+        command: Command,
+        output: &Path,
+        objects: &[PathBuf],
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let inputs = self.link_inputs(objects, options)?;
+        Self::link(
+            command,
+            output,
+            &inputs,
+            &format!("Linking {}", output.to_string_lossy()),
+            (error::Error::LinkerError, error::Error::RunLinkerError),
+            state,
+            options,
+        )
+    }
+
+    fn link_target(
+        &self,
+        objects: Vec<PathBuf>,
+        toolchain: &Toolchain,
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        match self.brew_type {
+            BrewType::Executable => self.link_executable(objects, toolchain, state, options),
+            BrewType::SharedLibrary => self.link_shared_library(objects, toolchain, state, options),
+            BrewType::Binary => self.link_binary(objects, toolchain, state, options),
+            _ => self.link_library(objects, toolchain, state, options),
+        }
+    }
+
+    fn link_executable(
+        &self,
+        objects: Vec<PathBuf>,
+        toolchain: &Toolchain,
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let targets = self.target_files()?;
+        let output = self.build_path(&targets[0], options);
+
+        let command = self.linker_command(&output, &objects, toolchain, options)?;
+        self.link_objects(command, &output, &objects, state, options)?;
+
+        Ok(())
+    }
+
+    fn link_library(
+        &self,
+        objects: Vec<PathBuf>,
+        toolchain: &Toolchain,
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let targets = self.target_files()?;
+        let output = self.build_path(&targets[0], options);
+
+        let mut command = Command::new(toolchain.archiver().executable());
+        command.args(toolchain.archiver().flags());
+        command.arg(&output);
+        command.args(&objects);
+        self.link_objects(command, &output, &objects, state, options)?;
+
+        Ok(())
+    }
+
+    fn link_shared_library(
+        &self,
+        objects: Vec<PathBuf>,
+        toolchain: &Toolchain,
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let targets = self.target_files()?;
+        let output = self.build_path(&targets[0], options);
+        let soname = &targets[targets.len().saturating_sub(2)];
+
+        let mut command = self.linker_command(&output, &objects, toolchain, options)?;
+        command.arg("-shared");
+        command.arg(format!("-Wl,-soname,{}", soname));
+        self.link_objects(command, &output, &objects, state, options)?;
+
+        Self::create_symlinks(&targets, &self.build_path(".", options))
+    }
+
+    // Links a static image under the objects directory and converts it to a
+    // raw binary
+    fn link_binary(
+        &self,
+        objects: Vec<PathBuf>,
+        toolchain: &Toolchain,
+        state: &mut State,
+        options: &Options,
+    ) -> Result<(), error::Error> {
+        let targets = self.target_files()?;
+        let output = self.build_path(&targets[0], options);
+        let image = self.binary_image(options)?;
+
+        let mut command = self.linker_command(&image, &objects, toolchain, options)?;
+        command.arg("-nostdlib");
+        command.arg("-static");
+        self.link_objects(command, &image, &objects, state, options)?;
+
+        // The conversion is recorded separately so changing the objcopy
+        // command converts the image again
+        let mut command = Command::new(toolchain.objcopy().executable());
+        command.args(toolchain.objcopy().flags());
+        command.arg(&image);
+        command.arg(&output);
+
+        Self::link(
+            command,
+            &output,
+            std::slice::from_ref(&image),
+            &format!(
+                "Converting {} to {}",
+                image.to_string_lossy(),
+                output.to_string_lossy()
+            ),
+            (error::Error::ObjcopyError, error::Error::RunObjcopyError),
+            state,
+            options,
+        )
+    }
+
+    // Returns the static image a binary is converted from
+    fn binary_image(&self, options: &Options) -> Result<PathBuf, error::Error> {
+        let targets = self.target_files()?;
+        Ok(self
+            .objects_path(options)
+            .join(&targets[0])
+            .with_extension("elf"))
+    }
+
+    // Returns the file produced by linking followed by the names of any
+    // symbolic links to it, each link pointing at the name before it
+    fn target_files(&self) -> Result<Vec<String>, error::Error> {
+        let name = match &self.name {
+            Some(name) => name,
             None => return Err(error::Error::NoName),
         };
 
-        let mut command = Command::new(crate::config::ARCHIVER);
-        command.args(crate::config::ARCHIVER_FLAGS);
-        command.arg(&output);
-        command.args(objects);
+        Ok(match self.brew_type {
+            BrewType::Executable => vec![match &self.output {
+                Some(output) => output.to_owned(),
+                None => format!("{}.app", name),
+            }],
+            BrewType::Binary => vec![match &self.output {
+                Some(output) => output.to_owned(),
+                None => format!("{}.bin", name),
+            }],
+            BrewType::SharedLibrary => {
+                let library = format!("lib{}.so", name);
+                match &self.version {
+                    Some(version) => {
+                        let major = version.split('.').next().unwrap();
+                        let soname = format!("{}.{}", library, major);
+                        let versioned = format!("{}.{}", library, version);
+                        if versioned == soname {
+                            vec![soname, library]
+                        } else {
+                            vec![versioned, soname, library]
+                        }
+                    }
+                    None => vec![library],
+                }
+            }
+            _ => vec![format!("lib{}.a", name)],
+        })
+    }
 
-        if !options.quiet() {
-            println!("Linking {} . . .", output);
-        }
+    fn create_symlinks(targets: &[String], directory: &Path) -> Result<(), error::Error> {
+        for pair in targets.windows(2) {
+            let link = directory.join(&pair[1]);
+            if link.symlink_metadata().is_ok() {
+                match std::fs::remove_file(&link) {
+                    Ok(()) => {}
+                    Err(error) => return Err(error::Error::SymlinkError(link, error)),
+                }
+            }
 
-        match command.status() {
-            Ok(status) => match status.success() {
-                true => Ok(()),
-                false => Err(error::Error::LinkerError),
-            },
-            Err(error) => Err(error::Error::RunLinkerError(error)),
+            match std::os::unix::fs::symlink(&pair[0], &link) {
+                Ok(()) => {}
+                Err(error) => return Err(error::Error::SymlinkError(link, error)),
+            }
         }
+
+        Ok(())
     }
 
-    fn brew_sub_directory(&self, path: PathBuf, options: &Options) -> Result<(), error::Error> {
+    fn brew_sub_directory(
+        &self,
+        path: PathBuf,
+        siblings: &[(String, PathBuf)],
+        options: &Options,
+    ) -> Result<(), error::Error> {
         // Brew sub directory
         let sysroot = if options.sysroot().has_root() {
             options.sysroot().to_owned()
@@ -274,12 +966,64 @@ impl Brewfile {
         command.arg(sysroot);
         command.arg("--prefix");
         command.arg(prefix);
+        command.arg("--jobs");
+        command.arg(options.jobs().to_string());
+        command.arg("--profile");
+        command.arg(options.profile());
+        // A group's toolchain applies to each of its projects
+        if let Some(toolchain) = options.toolchain().or(self.toolchain()) {
+            command.arg("--toolchain");
+            if toolchain.has_root() {
+                command.arg(toolchain);
+            } else {
+                command.arg(PathBuf::from("..").join(toolchain));
+            }
+        }
         if options.verbose() {
             command.arg("-v");
         }
         if options.quiet() {
             command.arg("-q");
         }
+        if options.hash() {
+            command.arg("--hash");
+        }
+        for (name, value) in options.defines() {
+            command.arg("--define");
+            command.arg(format!("{}={}", name, value));
+        }
+        // Each project gets its own directory under the group's one
+        if let Some(build_dir) = self.build_directory(options) {
+            let build_dir = build_dir.join(path.file_name().unwrap());
+            command.arg("--build-dir");
+            if build_dir.has_root() {
+                command.arg(build_dir);
+            } else {
+                command.arg(PathBuf::from("..").join(build_dir));
+            }
+        }
+        if let Some(cache_dir) = options.cache_dir() {
+            command.arg("--cache-dir");
+            if cache_dir.has_root() {
+                command.arg(cache_dir);
+            } else {
+                command.arg(PathBuf::from("..").join(cache_dir));
+            }
+        }
+        // Projects in this group hide ones with the same name further out
+        let inherited = options
+            .siblings()
+            .iter()
+            .filter(|(name, _)| !siblings.iter().any(|(sibling, _)| sibling == name));
+        for (name, directory) in siblings.iter().chain(inherited) {
+            let directory = if directory.has_root() {
+                directory.to_owned()
+            } else {
+                normalise(&PathBuf::from("..").join(directory))
+            };
+            command.arg("--sibling");
+            command.arg(format!("{}={}", name, directory.to_string_lossy()));
+        }
 
         command.current_dir(&path);
 
@@ -304,10 +1048,7 @@ impl Brewfile {
     }
 
     fn brew_sub_folders(&self, options: Options) -> Result<(), error::Error> {
-        // Brew priority first
-        for priority in &self.priority {
-            self.brew_sub_directory(priority.to_owned(), &options)?;
-        }
+        let mut directories = self.priority.clone();
 
         let directory = match std::fs::read_dir(".") {
             Ok(dir) => dir,
@@ -334,17 +1075,89 @@ impl Brewfile {
                 }
             }
 
-            if path.is_dir() && !path.file_name().unwrap().to_string_lossy().starts_with(".") {
-                self.brew_sub_directory(path, &options)?;
+            // The build directory may be inside the group
+            if let Some(build_dir) = self.build_directory(&options) {
+                if normalise(&path) == normalise(build_dir) {
+                    continue;
+                }
+            }
+
+            if path.is_dir() {
+                directories.push(path);
+            }
+        }
+
+        // Brew dependencies before the projects that need them
+        let order = group::build_order(directories, &self.priority, &options)?;
+        if options.verbose() {
+            println!("Brew order:");
+            for (path, _) in &order {
+                println!(" - {}", path.to_string_lossy());
+            }
+            println!();
+        }
+
+        // Tell each project where the others' targets will be
+        let mut siblings = Vec::new();
+        for (path, name) in &order {
+            if let Some(name) = name {
+                let directory = match self.build_directory(&options) {
+                    Some(build_dir) => build_dir.join(path.file_name().unwrap()),
+                    None => path.to_owned(),
+                };
+                siblings.push((name.to_owned(), directory));
+            }
+        }
+
+        for (path, _) in &order {
+            // Remove the project's old database so only the one written by
+            // this run is merged
+            if let crate::arguments::Command::CompileCommands = options.command() {
+                let database = path.join(crate::config::COMPILE_COMMANDS_NAME);
+                if database.exists() {
+                    match std::fs::remove_file(&database) {
+                        Ok(()) => {}
+                        Err(error) => {
+                            return Err(error::Error::RemoveTargetError(
+                                format!("{}", database.to_string_lossy()),
+                                error,
+                            ))
+                        }
+                    }
+                }
+            }
+
+            self.brew_sub_directory(path.to_owned(), &siblings, &options)?;
+        }
+
+        // The projects' build directories have been removed, so remove the
+        // group's one if nothing else was put in it
+        if let crate::arguments::Command::Clean = options.command() {
+            if let Some(directory) = self.build_directory(&options) {
+                std::fs::remove_dir(directory).ok();
+            }
+        }
+
+        // Combine the sub-projects' compilation databases
+        if let crate::arguments::Command::CompileCommands = options.command() {
+            let databases: Vec<PathBuf> = order
+                .iter()
+                .map(|(path, _)| path.join(crate::config::COMPILE_COMMANDS_NAME))
+                .collect();
+
+            if !options.quiet() {
+                println!("Writing {} . . .", crate::config::COMPILE_COMMANDS_NAME);
             }
+
+            compile_commands::merge(Path::new(crate::config::COMPILE_COMMANDS_NAME), &databases)?;
         }
 
         Ok(())
     }
 
-    fn clean(&self) -> Result<(), error::Error> {
+    fn clean(&self, options: &Options) -> Result<(), error::Error> {
         // Remove object directory
-        let object_directory = PathBuf::from(crate::config::OBJECTS_PATH);
+        let object_directory = self.build_path(crate::config::OBJECTS_PATH, options);
         if object_directory.exists() {
             match std::fs::remove_dir_all(&object_directory) {
                 Ok(()) => {}
@@ -357,40 +1170,81 @@ impl Brewfile {
             object.clean()?;
         }
 
-        // Remove target
-        let name = match &self.name {
-            Some(name) => name,
-            None => return Err(error::Error::NoName),
-        };
-
-        let target = PathBuf::from(match self.brew_type {
-            BrewType::Executable => format!("{}.app", name),
-            _ => format!("lib{}.a", name),
-        });
-
-        if target.exists() {
-            match std::fs::remove_file(&target) {
-                Ok(()) => Ok(()),
-                Err(error) => Err(error::Error::RemoveTargetError(
-                    format!("{}", target.to_string_lossy()),
-                    error,
-                )),
+        // Remove target and any links to it
+        for target in self.target_files()? {
+            let target = self.build_path(target, options);
+            if target.symlink_metadata().is_ok() {
+                match std::fs::remove_file(&target) {
+                    Ok(()) => {}
+                    Err(error) => {
+                        return Err(error::Error::RemoveTargetError(
+                            format!("{}", target.to_string_lossy()),
+                            error,
+                        ))
+                    }
+                }
             }
-        } else {
-            Ok(())
         }
+
+        // Remove the build directory if nothing else was put in it
+        if let Some(directory) = self.build_directory(options) {
+            std::fs::remove_dir(directory).ok();
+        }
+
+        Ok(())
     }
 
-    pub fn execute(self, options: Options) -> Result<(), error::Error> {
+    pub fn execute(
+        mut self,
+        options: Options,
+        mut toolchain: Toolchain,
+    ) -> Result<(), error::Error> {
         match self.brew_type {
             BrewType::Group => return Ok(self.brew_sub_folders(options)?),
             BrewType::None => return Err(error::Error::NoBrewType),
             _ => {}
         }
 
+        self.apply_flags(&mut toolchain, &options)?;
+
+        if let Some(directory) = self.build_directory(&options).map(Path::to_owned) {
+            for object in &mut self.objects {
+                object.relocate(&directory);
+            }
+        }
+
+        // Shared libraries must be position independent
+        if let BrewType::SharedLibrary = self.brew_type {
+            for language in [Language::C, Language::CPlusPlus] {
+                toolchain
+                    .compiler_mut(language)
+                    .add_flag("-fPIC".to_owned());
+            }
+        }
+
         match options.command() {
             crate::arguments::Command::Build | crate::arguments::Command::Install => {
-                let objects = self.compile_source_directory(&options)?;
+                let mut state =
+                    State::load(self.objects_path(&options).join(crate::config::STATE_NAME));
+
+                // Targets are shared by every profile, so they are recorded
+                // outside the profile's objects directory. Their commands
+                // name the profile's objects, so switching profiles relinks.
+                let mut target_state = State::load(
+                    self.build_path(crate::config::OBJECTS_PATH, &options)
+                        .join(crate::config::STATE_NAME),
+                );
+
+                let cache = options
+                    .cache_dir()
+                    .map(|directory| Cache::new(directory.to_owned()));
+
+                let objects = self.compile_source_directory(
+                    &toolchain,
+                    &mut state,
+                    cache.as_ref(),
+                    &options,
+                )?;
 
                 if options.verbose() {
                     println!("Objects to link:");
@@ -399,14 +1253,28 @@ impl Brewfile {
                     }
                 }
 
-                match self.brew_type {
-                    BrewType::Executable => self.link_executable(objects, &options)?,
-                    _ => self.link_library(objects, &options)?,
-                }
-
-                // Compile objects
-                for object in &self.objects {
-                    object.compile(&options)?;
+                // Record the steps which succeed, even if a later one fails
+                let result = self
+                    .link_target(objects, &toolchain, &mut target_state, &options)
+                    .and_then(|()| {
+                        // Compile objects
+                        for object in &self.objects {
+                            object.compile(
+                                &toolchain,
+                                &mut target_state,
+                                cache.as_ref(),
+                                &options,
+                            )?;
+                        }
+                        Ok(())
+                    });
+                target_state.save()?;
+                result?;
+
+                if let Some(cache) = &cache {
+                    if options.verbose() {
+                        println!("Cache: {} hits, {} misses", cache.hits(), cache.misses());
+                    }
                 }
 
                 match options.command() {
@@ -417,46 +1285,36 @@ impl Brewfile {
                         }
 
                         // Install target
-                        let mut target_path = options.prefix().to_owned();
-                        let source = match self.brew_type {
-                            BrewType::Executable => {
-                                target_path.push("bin");
-                                format!(
-                                    "{}.app",
-                                    match self.name {
-                                        Some(name) => name,
-                                        None => return Err(error::Error::NoName),
-                                    }
-                                )
-                            }
-                            _ => {
-                                target_path.push("lib");
-                                format!(
-                                    "lib{}.a",
-                                    match self.name {
-                                        Some(name) => name,
-                                        None => return Err(error::Error::NoName),
-                                    }
-                                )
-                            }
-                        };
+                        let target_path = options.prefix().join(match &self.install_dir {
+                            Some(install_dir) => install_dir.as_path(),
+                            None => match self.brew_type {
+                                BrewType::Executable | BrewType::Binary => Path::new("bin"),
+                                _ => Path::new("lib"),
+                            },
+                        });
 
-                        target_path.push(&source);
+                        let targets = self.target_files()?;
+                        let source = self.build_path(&targets[0], &options);
 
                         if !options.quiet() {
-                            println!("Installing {} . . .", &source);
+                            println!("Installing {} . . .", source.to_string_lossy());
                         }
 
-                        match std::fs::copy(&source, target_path) {
+                        match std::fs::copy(&source, target_path.join(&targets[0])) {
                             Ok(_) => {}
                             Err(error) => {
-                                return Err(error::Error::InstallTargetError(source, error))
+                                return Err(error::Error::InstallTargetError(
+                                    format!("{}", source.to_string_lossy()),
+                                    error,
+                                ))
                             }
                         }
 
+                        Self::create_symlinks(&targets, &target_path)?;
+
                         // Install headers
                         match self.brew_type {
-                            BrewType::Library => {
+                            BrewType::Library | BrewType::SharedLibrary => {
                                 let include_path = PathBuf::from("./include");
                                 if include_path.exists() {
                                     Self::install_include_directory(
@@ -474,7 +1332,10 @@ impl Brewfile {
 
                 Ok(())
             }
-            crate::arguments::Command::Clean => Ok(self.clean()?),
+            crate::arguments::Command::CompileCommands => {
+                self.write_compile_commands(&toolchain, &options)
+            }
+            crate::arguments::Command::Clean => Ok(self.clean(&options)?),
         }
     }
 }
@@ -488,6 +1349,40 @@ impl std::fmt::Display for Brewfile {
 
         writeln!(f, "Brew Type: {}", self.brew_type)?;
 
+        match &self.version {
+            None => {}
+            Some(version) => writeln!(f, "Version: {}", version)?,
+        }
+
+        match &self.output {
+            None => {}
+            Some(output) => writeln!(f, "Output: {}", output)?,
+        }
+
+        match &self.install_dir {
+            None => {}
+            Some(install_dir) => {
+                writeln!(f, "Install Directory: {}", install_dir.to_string_lossy())?
+            }
+        }
+
+        match &self.build_dir {
+            None => {}
+            Some(build_dir) => writeln!(f, "Build Directory: {}", build_dir.to_string_lossy())?,
+        }
+
+        match &self.linker_script {
+            None => {}
+            Some(linker_script) => {
+                writeln!(f, "Linker Script: {}", linker_script.to_string_lossy())?
+            }
+        }
+
+        match &self.entry {
+            None => {}
+            Some(entry) => writeln!(f, "Entry: {}", entry)?,
+        }
+
         if self.languages.len() > 0 {
             writeln!(f, "Languages:")?;
             for language in &self.languages {
@@ -495,6 +1390,9 @@ impl std::fmt::Display for Brewfile {
             }
         }
 
+        writeln!(f, "Extensions:")?;
+        write!(f, "{}", self.extensions)?;
+
         if self.dependencies.len() > 0 {
             writeln!(f, "Dependencies:")?;
             for dependency in &self.dependencies {
@@ -502,6 +1400,13 @@ impl std::fmt::Display for Brewfile {
             }
         }
 
+        write!(f, "{}", self.flags)?;
+
+        for (name, flags) in &self.profiles {
+            writeln!(f, "Profile {}:", name)?;
+            write!(f, "{}", flags)?;
+        }
+
         if self.objects.len() > 0 {
             writeln!(f, "Objects:")?;
             for object in &self.objects {
@@ -522,6 +1427,8 @@ impl std::fmt::Display for BrewType {
                 BrewType::None => "None",
                 BrewType::Executable => "Executable",
                 BrewType::Library => "Library",
+                BrewType::SharedLibrary => "Shared Library",
+                BrewType::Binary => "Binary",
                 BrewType::Group => "Group",
             }
         )
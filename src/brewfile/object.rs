@@ -1,7 +1,9 @@
 use crate::arguments::Options;
 
-use super::{error::Error, Language};
-use std::path::PathBuf;
+use super::{
+    cache::Cache, dependency_file, error::Error, job::Job, state::State, Language, Toolchain,
+};
+use std::path::{Path, PathBuf};
 
 pub struct Object {
     input_filename: PathBuf,
@@ -20,14 +22,44 @@ impl Object {
         }
     }
 
-    pub fn compile(&self, options: &Options) -> Result<(), Error> {
-        match self
-            .language
-            .compile(&self.input_filename, &self.output_filename, options)
-        {
-            Ok(_) => Ok(()),
-            Err(error) => Err(error),
-        }
+    pub fn compile(
+        &self,
+        toolchain: &Toolchain,
+        state: &mut State,
+        cache: Option<&Cache>,
+        options: &Options,
+    ) -> Result<(), Error> {
+        self.language.compile(
+            &self.input_filename,
+            &self.output_filename,
+            toolchain,
+            state,
+            cache,
+            options,
+        )
+    }
+
+    // Moves the output into "directory", keeping its path relative to the
+    // project
+    pub fn relocate(&mut self, directory: &Path) {
+        let output = self
+            .output_filename
+            .strip_prefix(".")
+            .unwrap_or(&self.output_filename);
+        self.output_filename = directory.join(output);
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output_filename
+    }
+
+    pub fn job(&self, toolchain: &Toolchain, options: &Options) -> Job {
+        self.language.job(
+            &self.input_filename,
+            &self.output_filename,
+            toolchain,
+            options,
+        )
     }
 
     pub fn install(&self, options: &Options) -> Result<(), Error> {
@@ -51,11 +83,16 @@ impl Object {
     }
 
     pub fn clean(&self) -> Result<(), Error> {
-        if self.output_filename.exists() {
-            match std::fs::remove_file(&self.output_filename) {
+        Self::remove_file(&self.output_filename)?;
+        Self::remove_file(&dependency_file::path(&self.output_filename))
+    }
+
+    fn remove_file(path: &Path) -> Result<(), Error> {
+        if path.exists() {
+            match std::fs::remove_file(path) {
                 Ok(_) => Ok(()),
                 Err(error) => Err(Error::RemoveTargetError(
-                    format!("{}", self.output_filename.to_string_lossy()),
+                    format!("{}", path.to_string_lossy()),
                     error,
                 )),
             }
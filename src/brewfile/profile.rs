@@ -0,0 +1,31 @@
+use super::{Flags, Language};
+
+fn strings(list: &[&str]) -> Vec<String> {
+    list.iter().map(|string| (*string).to_owned()).collect()
+}
+
+// Returns the flags of a built-in profile
+pub fn builtin(name: &str) -> Option<Flags> {
+    let mut flags = Flags::new();
+
+    match name {
+        "debug" => {
+            let compiler_flags = strings(&crate::config::DEBUG_COMPILER_FLAGS);
+            flags.add_compiler_flags(Language::C, compiler_flags.clone());
+            flags.add_compiler_flags(Language::CPlusPlus, compiler_flags);
+            flags.add_compiler_flags(
+                Language::Assembly,
+                strings(&crate::config::DEBUG_ASSEMBLER_FLAGS),
+            );
+        }
+        "release" => {
+            let compiler_flags = strings(&crate::config::RELEASE_COMPILER_FLAGS);
+            flags.add_compiler_flags(Language::C, compiler_flags.clone());
+            flags.add_compiler_flags(Language::CPlusPlus, compiler_flags);
+            flags.add_defines(strings(&crate::config::RELEASE_DEFINES));
+        }
+        _ => return None,
+    }
+
+    Some(flags)
+}
@@ -0,0 +1,57 @@
+use super::{cache::Cache, error::Error, job::Job};
+use crate::arguments::Options;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+// Runs the jobs on up to "options.jobs()" threads, calling "on_complete" for
+// each job which succeeds. No new jobs are started after the first failure,
+// but jobs already running are allowed to finish.
+pub fn run(
+    jobs: Vec<Job>,
+    cache: Option<&Cache>,
+    options: &Options,
+    on_complete: &(dyn Fn(&Job) + Sync),
+) -> Result<(), Error> {
+    if jobs.is_empty() {
+        return Ok(());
+    }
+
+    let workers = options.jobs().max(1).min(jobs.len());
+    let queue = Mutex::new(jobs.into_iter());
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if failed.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let job = match queue.lock().unwrap().next() {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                match job.run_captured(cache, options) {
+                    Ok(()) => on_complete(&job),
+                    Err(error) => {
+                        failed.store(true, Ordering::SeqCst);
+
+                        let mut first_error = first_error.lock().unwrap();
+                        if first_error.is_none() {
+                            *first_error = Some(error);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    match first_error.into_inner().unwrap() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,218 @@
+use super::{error::Error, hash, timestamp};
+use crate::arguments::Options;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// Records the command last used to build each target and, when hashing, the
+// hashes of its inputs. Each line of the file is a tab separated record
+// starting with the record type.
+pub struct State {
+    path: PathBuf,
+    commands: BTreeMap<PathBuf, Vec<String>>,
+    hashes: BTreeMap<PathBuf, BTreeMap<PathBuf, u64>>,
+}
+
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// Returns the program followed by its arguments
+pub fn command_line(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|argument| argument.to_string_lossy().into_owned())
+        .collect()
+}
+
+// Returns the hashes of "inputs" when up to date checks compare hashes
+pub fn input_hashes(inputs: &[PathBuf], options: &Options) -> Option<BTreeMap<PathBuf, u64>> {
+    match options.hash() {
+        true => hash::files(inputs.iter().map(|input| input.as_path())),
+        false => None,
+    }
+}
+
+// Returns true if "output" was last made by "command_line" and none of its
+// inputs changed since
+pub fn is_up_to_date(
+    output: &Path,
+    command_line: &[String],
+    inputs: &[PathBuf],
+    hashes: &Option<BTreeMap<PathBuf, u64>>,
+    state: &State,
+    options: &Options,
+) -> bool {
+    if !state.matches(output, command_line) {
+        return false;
+    }
+
+    match hashes {
+        Some(hashes) => output.exists() && state.hashes_match(output, hashes),
+        None => {
+            !options.hash()
+                && timestamp::is_up_to_date(output, inputs.iter().map(|input| input.as_path()))
+        }
+    }
+}
+
+// Records how "output" was made so later runs can tell if it changed
+pub fn record(
+    output: &Path,
+    command_line: Vec<String>,
+    hashes: Option<BTreeMap<PathBuf, u64>>,
+    state: &mut State,
+) {
+    state.set_command(output.to_owned(), command_line);
+    match hashes {
+        Some(hashes) => state.set_hashes(output.to_owned(), hashes),
+        None => state.remove_hashes(output),
+    }
+}
+
+impl State {
+    // A missing or unreadable state file is treated as empty, which causes
+    // everything to be rebuilt
+    pub fn load(path: PathBuf) -> Self {
+        let mut state = State {
+            path,
+            commands: BTreeMap::new(),
+            hashes: BTreeMap::new(),
+        };
+
+        let contents = match std::fs::read_to_string(&state.path) {
+            Ok(contents) => contents,
+            Err(_) => return state,
+        };
+
+        for line in contents.lines() {
+            let mut fields = line.split('\t').map(unescape);
+            match fields.next().as_deref() {
+                Some("command") => {
+                    if let Some(target) = fields.next() {
+                        state
+                            .commands
+                            .insert(PathBuf::from(target), fields.collect());
+                    }
+                }
+                Some("hash") => {
+                    let (target, input, hash) = match (fields.next(), fields.next(), fields.next())
+                    {
+                        (Some(target), Some(input), Some(hash)) => (target, input, hash),
+                        _ => continue,
+                    };
+
+                    if let Ok(hash) = u64::from_str_radix(&hash, 16) {
+                        state
+                            .hashes
+                            .entry(PathBuf::from(target))
+                            .or_default()
+                            .insert(PathBuf::from(input), hash);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        state
+    }
+
+    // Returns true if "target" was last built with "command_line"
+    pub fn matches(&self, target: &Path, command_line: &[String]) -> bool {
+        match self.commands.get(target) {
+            Some(recorded) => recorded.as_slice() == command_line,
+            None => false,
+        }
+    }
+
+    pub fn set_command(&mut self, target: PathBuf, command_line: Vec<String>) {
+        self.commands.insert(target, command_line);
+    }
+
+    // Returns true if the inputs of "target" have the same hashes as when it
+    // was last built
+    pub fn hashes_match(&self, target: &Path, hashes: &BTreeMap<PathBuf, u64>) -> bool {
+        match self.hashes.get(target) {
+            Some(recorded) => recorded == hashes,
+            None => false,
+        }
+    }
+
+    pub fn set_hashes(&mut self, target: PathBuf, hashes: BTreeMap<PathBuf, u64>) {
+        self.hashes.insert(target, hashes);
+    }
+
+    pub fn remove_hashes(&mut self, target: &Path) {
+        self.hashes.remove(target);
+    }
+
+    // Forgets everything recorded about "target"
+    pub fn remove(&mut self, target: &Path) {
+        self.commands.remove(target);
+        self.hashes.remove(target);
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let mut contents = String::new();
+        for (target, command_line) in &self.commands {
+            contents.push_str("command\t");
+            contents.push_str(&escape(&target.to_string_lossy()));
+            for argument in command_line {
+                contents.push('\t');
+                contents.push_str(&escape(argument));
+            }
+            contents.push('\n');
+        }
+
+        for (target, hashes) in &self.hashes {
+            for (input, hash) in hashes {
+                contents.push_str(&format!(
+                    "hash\t{}\t{}\t{:016x}\n",
+                    escape(&target.to_string_lossy()),
+                    escape(&input.to_string_lossy()),
+                    hash
+                ));
+            }
+        }
+
+        if let Some(parent) = self.path.parent() {
+            match std::fs::create_dir_all(parent) {
+                Ok(()) => {}
+                Err(error) => return Err(Error::DirectoryCreationError(parent.to_owned(), error)),
+            }
+        }
+
+        match std::fs::write(&self.path, contents) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Error::WriteStateError(self.path.clone(), error)),
+        }
+    }
+}
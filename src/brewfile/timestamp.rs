@@ -0,0 +1,26 @@
+use std::{path::Path, time::SystemTime};
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|meta| meta.modified()).ok()
+}
+
+// Returns true if "output" exists and no input was modified after it
+pub fn is_up_to_date<'a, I: IntoIterator<Item = &'a Path>>(output: &Path, inputs: I) -> bool {
+    let output_time = match modified_time(output) {
+        Some(time) => time,
+        None => return false,
+    };
+
+    for input in inputs {
+        match modified_time(input) {
+            Some(time) => {
+                if time > output_time {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+
+    true
+}
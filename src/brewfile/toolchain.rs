@@ -0,0 +1,126 @@
+use super::Language;
+
+#[derive(Clone)]
+pub struct Tool {
+    executable: String,
+    flags: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct Toolchain {
+    c_compiler: Tool,
+    cpp_compiler: Tool,
+    assembler: Tool,
+    linker: Tool,
+    archiver: Tool,
+    objcopy: Tool,
+}
+
+impl Tool {
+    fn new(executable: &str, flags: &[&str]) -> Self {
+        Tool {
+            executable: executable.to_owned(),
+            flags: flags.iter().map(|flag| (*flag).to_owned()).collect(),
+        }
+    }
+
+    pub fn executable(&self) -> &str {
+        &self.executable
+    }
+
+    pub fn flags(&self) -> &[String] {
+        &self.flags
+    }
+
+    pub fn set_executable(&mut self, executable: String) {
+        self.executable = executable;
+    }
+
+    pub fn set_flags(&mut self, flags: Vec<String>) {
+        self.flags = flags;
+    }
+
+    pub fn add_flag(&mut self, flag: String) {
+        self.flags.push(flag);
+    }
+}
+
+impl Toolchain {
+    pub fn compiler(&self, language: Language) -> &Tool {
+        match language {
+            Language::Assembly => &self.assembler,
+            Language::C => &self.c_compiler,
+            Language::CPlusPlus => &self.cpp_compiler,
+        }
+    }
+
+    pub fn compiler_mut(&mut self, language: Language) -> &mut Tool {
+        match language {
+            Language::Assembly => &mut self.assembler,
+            Language::C => &mut self.c_compiler,
+            Language::CPlusPlus => &mut self.cpp_compiler,
+        }
+    }
+
+    pub fn linker(&self) -> &Tool {
+        &self.linker
+    }
+
+    pub fn linker_mut(&mut self) -> &mut Tool {
+        &mut self.linker
+    }
+
+    pub fn archiver(&self) -> &Tool {
+        &self.archiver
+    }
+
+    pub fn archiver_mut(&mut self) -> &mut Tool {
+        &mut self.archiver
+    }
+
+    pub fn objcopy(&self) -> &Tool {
+        &self.objcopy
+    }
+
+    pub fn objcopy_mut(&mut self) -> &mut Tool {
+        &mut self.objcopy
+    }
+}
+
+// The built-in toolchain targets LOS
+impl Default for Toolchain {
+    fn default() -> Self {
+        Toolchain {
+            c_compiler: Tool::new(crate::config::C_COMPILER, &crate::config::C_COMPILER_FLAGS),
+            cpp_compiler: Tool::new(
+                crate::config::CPP_COMPILER,
+                &crate::config::CPP_COMPILER_FLAGS,
+            ),
+            assembler: Tool::new(crate::config::ASSEMBLER, &crate::config::ASSEMBLER_FLAGS),
+            linker: Tool::new(crate::config::LINKER, &crate::config::LINKER_FLAGS),
+            archiver: Tool::new(crate::config::ARCHIVER, &crate::config::ARCHIVER_FLAGS),
+            objcopy: Tool::new(crate::config::OBJCOPY, &crate::config::OBJCOPY_FLAGS),
+        }
+    }
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.executable)?;
+        for flag in &self.flags {
+            write!(f, " {}", flag)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "C Compiler: {}", self.c_compiler)?;
+        writeln!(f, "C++ Compiler: {}", self.cpp_compiler)?;
+        writeln!(f, "Assembler: {}", self.assembler)?;
+        writeln!(f, "Linker: {}", self.linker)?;
+        writeln!(f, "Archiver: {}", self.archiver)?;
+        writeln!(f, "Objcopy: {}", self.objcopy)
+    }
+}
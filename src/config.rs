@@ -1,22 +1,37 @@
 pub const BREWFILE_NAME: &str = "./brewfile";
+pub const COMPILE_COMMANDS_NAME: &str = "./compile_commands.json";
 
 pub const DEFAULT_PREFIX: &str = "/los/";
 pub const DEFAULT_SYSROOT: &str = "/";
 
+pub const DEFAULT_PROFILE: &str = "debug";
+
 pub const SOURCES_PATH: &str = "./src";
 pub const OBJECTS_PATH: &str = "./obj";
+pub const STATE_NAME: &str = ".brew-state";
+pub const CACHE_DIR_VARIABLE: &str = "BREW_CACHE_DIR";
+pub const BUILT_IN_VARIABLES: [&str; 4] = ["sysroot", "prefix", "profile", "name"];
 
 pub const C_COMPILER: &str = "clang";
-pub const C_COMPILER_FLAGS: [&str; 5] = ["--target=x86_64-los", "-Wall", "-g", "-c", "-I./include"];
+pub const C_COMPILER_FLAGS: [&str; 3] = ["--target=x86_64-los", "-Wall", "-I./include"];
 
 pub const CPP_COMPILER: &str = "clang++";
-pub const CPP_COMPILER_FLAGS: [&str; 5] = C_COMPILER_FLAGS;
+pub const CPP_COMPILER_FLAGS: [&str; 3] = C_COMPILER_FLAGS;
 
 pub const ASSEMBLER: &str = "nasm";
-pub const ASSEMBLER_FLAGS: [&str; 5] = ["-f", "elf64", "-g", "-F", "dwarf"];
+pub const ASSEMBLER_FLAGS: [&str; 2] = ["-f", "elf64"];
 
 pub const LINKER: &str = "clang";
 pub const LINKER_FLAGS: [&str; 1] = ["--target=x86_64-los"];
 
 pub const ARCHIVER: &str = "ar";
 pub const ARCHIVER_FLAGS: [&str; 1] = ["rcs"];
+
+pub const OBJCOPY: &str = "objcopy";
+pub const OBJCOPY_FLAGS: [&str; 2] = ["-O", "binary"];
+
+pub const DEBUG_COMPILER_FLAGS: [&str; 2] = ["-g", "-O0"];
+pub const DEBUG_ASSEMBLER_FLAGS: [&str; 3] = ["-g", "-F", "dwarf"];
+
+pub const RELEASE_COMPILER_FLAGS: [&str; 1] = ["-O2"];
+pub const RELEASE_DEFINES: [&str; 1] = ["NDEBUG"];
@@ -1,4 +1,8 @@
-use std::{env::args, process::exit};
+use std::{
+    env::args,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
 mod arguments;
 mod brewfile;
@@ -10,6 +14,16 @@ fn print_error(error: Box<dyn std::error::Error>) -> ! {
     exit(1);
 }
 
+fn change_directory(directory: &Path) -> Result<(), brewfile::error::Error> {
+    match std::env::set_current_dir(directory) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(brewfile::error::Error::ChangeDirectoryError(
+            directory.to_owned(),
+            error,
+        )),
+    }
+}
+
 fn main() {
     match run() {
         Ok(()) => {}
@@ -19,7 +33,48 @@ fn main() {
 
 fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Parse arguments
-    let options = arguments::parse_arguments(args().collect())?;
+    let mut options = arguments::parse_arguments(args().collect())?;
+    if options.help() {
+        print!("{}", arguments::usage());
+        return Ok(());
+    }
+    if options.version() {
+        println!("{}", arguments::version());
+        return Ok(());
+    }
+
+    // Change directory before doing anything else
+    if let Some(directory) = options.directory() {
+        change_directory(directory)?;
+    }
+
+    // Brew from the brewfile's directory so that the paths in it are
+    // relative to it, keeping the meaning of paths on the command line
+    let brewfile_path = match options.file() {
+        Some(_) => {
+            let current_directory = match std::env::current_dir() {
+                Ok(directory) => directory,
+                Err(error) => {
+                    return Err(Box::new(brewfile::error::Error::CurrentDirectoryError(
+                        error,
+                    )))
+                }
+            };
+            options.resolve_paths(&current_directory);
+
+            let file = options.file().unwrap();
+            if let Some(directory) = file.parent() {
+                change_directory(directory)?;
+            }
+
+            match file.file_name() {
+                Some(name) => Path::new(".").join(name),
+                None => file.to_owned(),
+            }
+        }
+        None => PathBuf::from(config::BREWFILE_NAME),
+    };
+
     if options.verbose() {
         println!("Options");
         println!("========================================");
@@ -27,7 +82,7 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Parse the brewfile
-    let brewfile = parser::parse_brewfile()?;
+    let brewfile = parser::parse_brewfile(&brewfile_path, &options)?;
     if options.verbose() {
         println!();
         println!("Brewfile");
@@ -35,8 +90,20 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         print!("{}", brewfile);
     }
 
+    // Load the toolchain, preferring one given on the command line
+    let toolchain = match options.toolchain().or(brewfile.toolchain()) {
+        Some(path) => parser::parse_toolchain(path, &options)?,
+        None => brewfile::Toolchain::default(),
+    };
+    if options.verbose() {
+        println!();
+        println!("Toolchain");
+        println!("========================================");
+        print!("{}", toolchain);
+    }
+
     // Execute the brewfile
-    brewfile.execute(options)?;
+    brewfile.execute(options, toolchain)?;
 
     Ok(())
 }
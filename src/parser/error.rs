@@ -1,13 +1,127 @@
+use std::path::{Path, PathBuf};
+
+// A position in a file along with the line it is on
+#[derive(Debug)]
+pub struct Location {
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    length: usize,
+    source_line: String,
+}
+
 #[derive(Debug)]
 pub enum BrewfileError {
-    ReadBrewfileError(std::io::Error),
+    ReadBrewfileError(PathBuf, std::io::Error),
     UnknownCharacter(char, usize, usize),
+    UnterminatedString(usize, usize),
+    UnknownEscape(char, usize, usize),
+    UndefinedVariable(String),
+    BuiltInVariable(String),
+    InvalidSubstitution(String),
+    UnterminatedIf,
+    ElseWithoutIf,
+    EndWithoutIf,
+    DuplicateElse,
     UnexpectedToken(&'static str, String),
     InvalidNumberOfParameters(String, usize, usize),
     AtleastParameters(String, usize, usize),
     UnknownBrewType(String),
+    UnknownToolchainKey(String),
+    InvalidProfileKey(String),
+    InvalidExtensionKey(String),
     TokenizeError(Box<dyn std::error::Error>),
     BrewfileError(crate::brewfile::error::Error),
+    Located(Box<BrewfileError>, Location),
+    // A key defined a second time, followed by its first definition
+    Duplicate(Box<BrewfileError>, Box<Location>, Box<Location>),
+    // The files in an include cycle, starting and ending with the same file
+    IncludeCycle(Vec<PathBuf>),
+    // An error in an included file, followed by the "include" line
+    Included(Box<BrewfileError>, Location),
+}
+
+impl Location {
+    // Points at "text", which starts at "line" and "column". An empty
+    // "text" marks the end of the line.
+    pub fn new(path: &Path, source: &str, line: usize, column: usize, text: &str) -> Self {
+        let lines: Vec<&str> = source.lines().collect();
+        let line = line.max(1).min(lines.len().max(1));
+        let source_line = lines.get(line - 1).copied().unwrap_or("");
+        let line_length = source_line.chars().count();
+
+        let (column, length) = match text.chars().count() {
+            0 => (line_length + 1, 1),
+            length => (column.max(1).min(line_length + 1), length),
+        };
+
+        Location {
+            path: path.to_owned(),
+            line,
+            column,
+            length,
+            source_line: source_line.to_owned(),
+        }
+    }
+
+    // Returns the source line with the location underlined. Tabs are kept in
+    // the padding so the underline lines up.
+    fn snippet(&self) -> String {
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "    {}\n    {}{}",
+            self.source_line,
+            padding,
+            "^".repeat(self.length)
+        )
+    }
+}
+
+impl BrewfileError {
+    // Returns the text and position of errors raised by the lexer
+    pub fn lexer_position(&self) -> Option<(String, usize, usize)> {
+        match self {
+            BrewfileError::UnknownCharacter(character, line, column) => {
+                Some((character.to_string(), *line, *column))
+            }
+            BrewfileError::UnterminatedString(line, column) => {
+                Some(("\"".to_string(), *line, *column))
+            }
+            BrewfileError::UnknownEscape(character, line, column) => {
+                Some((format!("\\{}", character), *line, *column))
+            }
+            _ => None,
+        }
+    }
+
+    // Returns the parameter the error is about, if any
+    pub fn parameter(&self) -> Option<&str> {
+        match self {
+            BrewfileError::UnknownBrewType(brew_type) => Some(brew_type),
+            BrewfileError::BrewfileError(crate::brewfile::error::Error::UnknownLanguage(
+                language,
+            )) => Some(language),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.path.to_string_lossy(),
+            self.line,
+            self.column
+        )
+    }
 }
 
 impl std::error::Error for BrewfileError {}
@@ -18,12 +132,36 @@ impl std::fmt::Display for BrewfileError {
             f,
             "{}",
             match self {
-                BrewfileError::ReadBrewfileError(error) =>
-                    format!("Unable to read brewfile ({})", error),
-                BrewfileError::UnknownCharacter(character, line, column) => format!(
-                    "Unknown character '{}' in brewfile at {}:{}",
-                    character, line, column
+                BrewfileError::ReadBrewfileError(path, error) => format!(
+                    "Unable to read brewfile {} ({})",
+                    path.to_string_lossy(),
+                    error
+                ),
+                // The position is given by the location wrapping this error
+                BrewfileError::UnknownCharacter(character, _, _) =>
+                    format!("Unknown character '{}' in brewfile", character),
+                BrewfileError::UnterminatedString(_, _) =>
+                    "Unterminated string in brewfile".to_string(),
+                BrewfileError::UndefinedVariable(name) =>
+                    format!("Undefined variable \"{}\" in brewfile", name),
+                BrewfileError::BuiltInVariable(name) => format!(
+                    "Unable to set the built-in variable \"{}\" in brewfile",
+                    name
                 ),
+                BrewfileError::InvalidSubstitution(parameter) => format!(
+                    "Invalid substitution in \"{}\" in brewfile (expected ${{name}} or $$)",
+                    parameter
+                ),
+                BrewfileError::UnterminatedIf =>
+                    "\"if\" without a matching \"end\" in brewfile".to_string(),
+                BrewfileError::ElseWithoutIf =>
+                    "\"else\" without a matching \"if\" in brewfile".to_string(),
+                BrewfileError::EndWithoutIf =>
+                    "\"end\" without a matching \"if\" in brewfile".to_string(),
+                BrewfileError::DuplicateElse =>
+                    "\"if\" with more than one \"else\" in brewfile".to_string(),
+                BrewfileError::UnknownEscape(character, _, _) =>
+                    format!("Unknown escape sequence '\\{}' in brewfile", character),
                 BrewfileError::UnexpectedToken(expected, actual) => format!(
                     "Expected {}, instead found {} in brewfile",
                     expected, actual
@@ -34,12 +172,46 @@ impl std::fmt::Display for BrewfileError {
                 ),
                 BrewfileError::UnknownBrewType(brew_type) =>
                     format!("Unknown brew type \"{}\" in brewfile", brew_type),
+                BrewfileError::UnknownToolchainKey(key) =>
+                    format!("Unknown toolchain key \"{}\"", key),
+                BrewfileError::InvalidProfileKey(key) => format!(
+                    "Invalid profile key \"{}\" in brewfile (expected profile.<name>.<flags>)",
+                    key
+                ),
+                BrewfileError::InvalidExtensionKey(key) => format!(
+                    "Invalid extension key \"{}\" in brewfile (expected extension.<extension>)",
+                    key
+                ),
                 BrewfileError::AtleastParameters(command, expected, actual) => format!(
                     "{} requires at least {} parameters but {} are specified in brewfile",
                     command, expected, actual
                 ),
                 BrewfileError::TokenizeError(error) => format!("{}", error),
                 BrewfileError::BrewfileError(error) => format!("{}", error),
+                BrewfileError::Located(error, location) =>
+                    format!("{}: {}\n{}", location, error, location.snippet()),
+                BrewfileError::Duplicate(error, second, first) => format!(
+                    "{}: {}\n{}\n{}: first defined here\n{}",
+                    second,
+                    error,
+                    second.snippet(),
+                    first,
+                    first.snippet()
+                ),
+                BrewfileError::IncludeCycle(cycle) => format!(
+                    "Include cycle in brewfile: {}",
+                    cycle
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                ),
+                BrewfileError::Included(error, location) => format!(
+                    "{}\n{}: included from here\n{}",
+                    error,
+                    location,
+                    location.snippet()
+                ),
             }
         )
     }
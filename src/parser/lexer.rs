@@ -1,12 +1,16 @@
 use compiler::lexer::{CharIter, Token};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TokenClass {
     EndOfFile,
     Newline,
     String(String),
+    // The value of a quoted string followed by the text it was written as
+    QuotedString(String, String),
     Comma,
     Equals,
+    DoubleEquals,
+    NotEquals,
 }
 
 fn tokenize_string(iter: &mut compiler::lexer::CharIter) -> Token<TokenClass> {
@@ -24,6 +28,9 @@ fn tokenize_string(iter: &mut compiler::lexer::CharIter) -> Token<TokenClass> {
             && c != '/'
             && c != '\\'
             && c != '.'
+            && c != '$'
+            && c != '{'
+            && c != '}'
         {
             iter.unget(c);
             break;
@@ -35,44 +42,136 @@ fn tokenize_string(iter: &mut compiler::lexer::CharIter) -> Token<TokenClass> {
     Token::new(TokenClass::String(string), token_line, token_column)
 }
 
+// Reads a double-quoted string after its opening quote, which may contain
+// any character other than a newline. "line" and "column" are the position of
+// the opening quote.
+fn tokenize_quoted_string(
+    iter: &mut compiler::lexer::CharIter,
+    line: usize,
+    column: usize,
+) -> Result<Token<TokenClass>, Box<dyn std::error::Error>> {
+    let mut string = String::new();
+    let mut text = String::from('"');
+    loop {
+        let escape_line = iter.line();
+        let escape_column = iter.column();
+        let c = match iter.next() {
+            Some('\n') | None => {
+                return Err(Box::new(super::error::BrewfileError::UnterminatedString(
+                    line, column,
+                )))
+            }
+            Some(c) => c,
+        };
+        text.push(c);
+
+        match c {
+            '"' => break,
+            '\\' => {
+                let c = match iter.next() {
+                    Some('\n') | None => {
+                        return Err(Box::new(super::error::BrewfileError::UnterminatedString(
+                            line, column,
+                        )))
+                    }
+                    Some(c) => c,
+                };
+                text.push(c);
+
+                match c {
+                    '"' => string.push('"'),
+                    '\\' => string.push('\\'),
+                    'n' => string.push('\n'),
+                    't' => string.push('\t'),
+                    'r' => string.push('\r'),
+                    // Substitution turns "$$" back into "$"
+                    '$' => string.push_str("$$"),
+                    c => {
+                        return Err(Box::new(super::error::BrewfileError::UnknownEscape(
+                            c,
+                            escape_line,
+                            escape_column,
+                        )))
+                    }
+                }
+            }
+            c => string.push(c),
+        }
+    }
+
+    Ok(Token::new(
+        TokenClass::QuotedString(string, text),
+        line,
+        column,
+    ))
+}
+
+// Every token is reported at the position of its first character
 pub fn get_next_token(
     iter: &mut CharIter,
 ) -> Result<Option<Token<TokenClass>>, Box<dyn std::error::Error>> {
+    let line = iter.line();
+    let column = iter.column();
     let c = iter.next().unwrap();
 
-    if c.is_alphabetic() || c == '_' || c == '/' || c == '\\' || c == '.' {
+    if c.is_alphanumeric() || c == '_' || c == '/' || c == '\\' || c == '.' || c == '-' || c == '$'
+    {
         iter.unget(c);
         Ok(Some(tokenize_string(iter)))
     } else {
         match c {
             '#' => {
-                // '#' start comments
-                while match iter.next() {
-                    Some(c) => c != '\n',
-                    None => false,
-                } {}
+                // '#' start comments, which run to the end of the line. The
+                // newline is left so a comment can end a line of parameters
+                // or an "if" line.
+                while let Some(c) = iter.next() {
+                    if c == '\n' {
+                        iter.unget(c);
+                        break;
+                    }
+                }
 
                 Ok(None)
             }
-            ',' => Ok(Some(Token::new(
-                TokenClass::Comma,
-                iter.line(),
-                iter.column(),
-            ))),
-            '=' => Ok(Some(Token::new(
-                TokenClass::Equals,
-                iter.line(),
-                iter.column(),
-            ))),
+            '"' => Ok(Some(tokenize_quoted_string(iter, line, column)?)),
+            ',' => Ok(Some(Token::new(TokenClass::Comma, line, column))),
+            '=' => match iter.next() {
+                Some('=') => Ok(Some(Token::new(TokenClass::DoubleEquals, line, column))),
+                Some(c) => {
+                    iter.unget(c);
+                    Ok(Some(Token::new(TokenClass::Equals, line, column)))
+                }
+                None => Ok(Some(Token::new(TokenClass::Equals, line, column))),
+            },
+            '!' => match iter.next() {
+                Some('=') => Ok(Some(Token::new(TokenClass::NotEquals, line, column))),
+                _ => Err(Box::new(super::error::BrewfileError::UnknownCharacter(
+                    '!', line, column,
+                ))),
+            },
             _ => Err(Box::new(super::error::BrewfileError::UnknownCharacter(
-                c,
-                iter.line(),
-                iter.column(),
+                c, line, column,
             ))),
         }
     }
 }
 
+impl TokenClass {
+    // Returns the text the token was read from, or nothing for the end of a
+    // line or file
+    pub fn text(&self) -> String {
+        match self {
+            TokenClass::EndOfFile | TokenClass::Newline => String::new(),
+            TokenClass::String(string) => string.clone(),
+            TokenClass::QuotedString(_, text) => text.clone(),
+            TokenClass::Comma => ",".to_string(),
+            TokenClass::Equals => "=".to_string(),
+            TokenClass::DoubleEquals => "==".to_string(),
+            TokenClass::NotEquals => "!=".to_string(),
+        }
+    }
+}
+
 impl compiler::lexer::TokenClass for TokenClass {}
 
 impl std::fmt::Display for TokenClass {
@@ -84,9 +183,116 @@ impl std::fmt::Display for TokenClass {
                 TokenClass::EndOfFile => format!("End of file"),
                 TokenClass::Newline => format!("Newline"),
                 TokenClass::String(string) => format!("\"{}\"", string),
+                TokenClass::QuotedString(_, text) => text.clone(),
                 TokenClass::Comma => format!("','"),
                 TokenClass::Equals => format!("'='"),
+                TokenClass::DoubleEquals => "'=='".to_string(),
+                TokenClass::NotEquals => "'!='".to_string(),
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TokenClass;
+    use crate::parser::error::BrewfileError;
+
+    // Returns each token with its line and column, up to the end of the file
+    fn tokenize(source: &str) -> Result<Vec<(TokenClass, usize, usize)>, BrewfileError> {
+        let mut tokens = match compiler::lexer::tokenize(
+            source.to_owned(),
+            super::get_next_token,
+            TokenClass::EndOfFile,
+            compiler::lexer::WhitespaceIgnore::AllExceptNewline(TokenClass::Newline),
+        ) {
+            Ok(tokens) => tokens,
+            Err(error) => return Err(*error.downcast::<BrewfileError>().unwrap()),
+        };
+
+        let mut result = Vec::new();
+        loop {
+            let token = tokens.next();
+            if let TokenClass::EndOfFile = token.class() {
+                return Ok(result);
+            }
+            result.push((token.class().clone(), token.line(), token.column()));
+        }
+    }
+
+    fn quoted(value: &str, text: &str) -> TokenClass {
+        TokenClass::QuotedString(value.to_owned(), text.to_owned())
+    }
+
+    #[test]
+    fn quoted_string_escapes() {
+        let source = r#""a\"b\\c\nd\te\rf""#;
+        let tokens = tokenize(source).unwrap();
+        assert_eq!(tokens, vec![(quoted("a\"b\\c\nd\te\rf", source), 1, 1)]);
+    }
+
+    #[test]
+    fn quoted_string_keeps_punctuation_and_tabs() {
+        let tokens = tokenize("\"-Wl,-T,a b=c\td#e\"").unwrap();
+        assert_eq!(
+            tokens,
+            vec![(quoted("-Wl,-T,a b=c\td#e", "\"-Wl,-T,a b=c\td#e\""), 1, 1)]
+        );
+    }
+
+    // An escaped "$" becomes "$$" so substitution leaves a single "$"
+    #[test]
+    fn quoted_string_escaped_dollar() {
+        let tokens = tokenize(r#""\${name}""#).unwrap();
+        assert_eq!(tokens, vec![(quoted("$${name}", r#""\${name}""#), 1, 1)]);
+    }
+
+    #[test]
+    fn empty_quoted_string() {
+        let tokens = tokenize(r#""""#).unwrap();
+        assert_eq!(tokens, vec![(quoted("", r#""""#), 1, 1)]);
+    }
+
+    #[test]
+    fn unterminated_quoted_string() {
+        for source in ["\"abc", "\"abc\nx", "\"abc\\", "\"abc\\\n"] {
+            match tokenize(source) {
+                Err(BrewfileError::UnterminatedString(1, 1)) => {}
+                result => panic!("{:?} gave {:?}", source, result),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_escape() {
+        match tokenize(r#"x = "ab\q""#) {
+            Err(BrewfileError::UnknownEscape('q', 1, 8)) => {}
+            result => panic!("{:?}", result),
+        }
+    }
+
+    // Newlines are made by the tokenizer rather than "get_next_token", so
+    // only the other tokens are checked
+    #[test]
+    fn token_columns() {
+        let tokens: Vec<(TokenClass, usize, usize)> = tokenize("a = \"b\tc\", d\nif x != y\n")
+            .unwrap()
+            .into_iter()
+            .filter(|(class, _, _)| *class != TokenClass::Newline)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenClass::String("a".to_owned()), 1, 1),
+                (TokenClass::Equals, 1, 3),
+                (quoted("b\tc", "\"b\tc\""), 1, 5),
+                (TokenClass::Comma, 1, 10),
+                (TokenClass::String("d".to_owned()), 1, 12),
+                (TokenClass::String("if".to_owned()), 2, 1),
+                (TokenClass::String("x".to_owned()), 2, 4),
+                (TokenClass::NotEquals, 2, 6),
+                (TokenClass::String("y".to_owned()), 2, 9),
+            ]
+        );
+    }
+}
@@ -1,67 +1,430 @@
-use crate::brewfile::{BrewType, Brewfile, Language, Object};
-use std::path::PathBuf;
+use crate::arguments::Options;
+use crate::brewfile::{BrewType, Brewfile, FileClass, Flags, Language, Object, Toolchain};
+use std::path::{Path, PathBuf};
+use variables::Variables;
 
 mod error;
 mod lexer;
+mod variables;
 
-pub fn parse_brewfile() -> Result<Brewfile, error::BrewfileError> {
-    // Read brewfile
-    let source = match std::fs::read_to_string(crate::config::BREWFILE_NAME) {
+pub fn parse_brewfile(path: &Path, options: &Options) -> Result<Brewfile, error::BrewfileError> {
+    let mut brewfile = Brewfile::new();
+    let file = parse_file(path, Variables::new(options), |command, parameters| {
+        parse_command(command, parameters, &mut brewfile)
+    })?;
+
+    // Check the keys which are only invalid in combination
+    match brewfile.validate() {
+        Ok(()) => Ok(brewfile),
+        Err(error) => Err(file.locate(error.into())),
+    }
+}
+
+// Toolchain files use the brewfile syntax and override the built-in toolchain
+pub fn parse_toolchain(path: &Path, options: &Options) -> Result<Toolchain, error::BrewfileError> {
+    let mut toolchain = Toolchain::default();
+    parse_file(path, Variables::new(options), |command, parameters| {
+        parse_toolchain_command(command, parameters, &mut toolchain)
+    })?;
+    Ok(toolchain)
+}
+
+// Where a token was found, as reported by the lexer. "source" is the index
+// of the file it is in.
+#[derive(Clone, Copy)]
+struct Position {
+    source: usize,
+    line: usize,
+    column: usize,
+}
+
+// A parameter's value along with the text it was written as
+struct Parameter {
+    value: String,
+    text: String,
+    position: Position,
+}
+
+// A "key = parameter, ..." line with the positions of its tokens
+struct Entry {
+    key: String,
+    position: Position,
+    parameters: Vec<Parameter>,
+}
+
+// An "if" block which has not been ended
+struct Condition {
+    // True if the lines in the current branch are used
+    active: bool,
+    // True if the block is itself in a branch which is used
+    enclosing_active: bool,
+    // True if the condition held, so the "else" branch is not used
+    taken: bool,
+    in_else: bool,
+    position: Position,
+}
+
+// A file read by the parser, either the brewfile itself or an included one
+struct Source {
+    path: PathBuf,
+    source: String,
+}
+
+// The entries of a parsed file and the files it included, kept so errors can
+// be traced back to them
+struct File {
+    sources: Vec<Source>,
+    // The source being parsed
+    current: usize,
+    entries: Vec<Entry>,
+}
+
+impl File {
+    fn position(&self, line: usize, column: usize) -> Position {
+        Position {
+            source: self.current,
+            line,
+            column,
+        }
+    }
+
+    fn location(&self, text: &str, position: Position) -> error::Location {
+        let source = &self.sources[position.source];
+        error::Location::new(
+            &source.path,
+            &source.source,
+            position.line,
+            position.column,
+            text,
+        )
+    }
+
+    // Attaches the position of the entry responsible for "error". Keys which
+    // were defined twice point to both definitions, otherwise errors point
+    // to the offending parameter or the key of the last entry.
+    fn locate(&self, error: error::BrewfileError) -> error::BrewfileError {
+        let occurrences = self.occurrences(&error);
+        if occurrences.len() >= 2 {
+            let second = self.location(occurrences[1].0, occurrences[1].1);
+            let first = self.location(occurrences[0].0, occurrences[0].1);
+            return error::BrewfileError::Duplicate(
+                Box::new(error),
+                Box::new(second),
+                Box::new(first),
+            );
+        }
+
+        let (text, position) = match occurrences.into_iter().next() {
+            Some(occurrence) => occurrence,
+            None => match self.entries.last() {
+                Some(entry) => {
+                    let parameter = error.parameter().and_then(|value| {
+                        entry
+                            .parameters
+                            .iter()
+                            .find(|parameter| parameter.value == value)
+                    });
+
+                    match parameter {
+                        Some(parameter) => (parameter.text.as_str(), parameter.position),
+                        None => (entry.key.as_str(), entry.position),
+                    }
+                }
+                None => return error,
+            },
+        };
+
+        let location = self.location(text, position);
+        error::BrewfileError::Located(Box::new(error), location)
+    }
+
+    // Returns the definitions "error" is about, for errors about a key
+    fn occurrences(&self, error: &error::BrewfileError) -> Vec<(&str, Position)> {
+        let error = match error {
+            error::BrewfileError::BrewfileError(error) => error,
+            _ => return Vec::new(),
+        };
+
+        let key = match error.key() {
+            Some(key) => key,
+            None => return Vec::new(),
+        };
+
+        let mut occurrences = Vec::new();
+        for entry in self.entries.iter().filter(|entry| entry.key == key) {
+            match error {
+                // Repeated list items point to the items themselves
+                crate::brewfile::error::Error::LanguageDefinedTwice(language) => {
+                    for parameter in &entry.parameters {
+                        if Language::parse(&parameter.value).ok() == Some(*language) {
+                            occurrences.push((parameter.text.as_str(), parameter.position));
+                        }
+                    }
+                }
+                crate::brewfile::error::Error::DependencyDefinedTwice(dependency) => {
+                    for parameter in &entry.parameters {
+                        if &parameter.value == dependency {
+                            occurrences.push((parameter.text.as_str(), parameter.position));
+                        }
+                    }
+                }
+                _ => occurrences.push((entry.key.as_str(), entry.position)),
+            }
+        }
+
+        occurrences
+    }
+}
+
+// Calls "handle_command" for every "command = parameter, ..." line in a file,
+// defining variables for "let name = parameter, ..." lines and parsing the
+// files named by "include = path, ..." lines in place
+fn parse_file<F: FnMut(&str, Vec<String>) -> Result<(), error::BrewfileError>>(
+    path: &Path,
+    mut variables: Variables,
+    mut handle_command: F,
+) -> Result<File, error::BrewfileError> {
+    let mut file = File {
+        sources: Vec::new(),
+        current: 0,
+        entries: Vec::new(),
+    };
+
+    parse_source(
+        &mut file,
+        path,
+        &mut variables,
+        &mut handle_command,
+        &mut Vec::new(),
+    )?;
+    Ok(file)
+}
+
+// Parses one file into "file". "includes" holds the canonical and given paths
+// of the files currently being parsed, so an include cycle can be reported.
+fn parse_source<F: FnMut(&str, Vec<String>) -> Result<(), error::BrewfileError>>(
+    file: &mut File,
+    path: &Path,
+    variables: &mut Variables,
+    handle_command: &mut F,
+    includes: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), error::BrewfileError> {
+    let canonical = match std::fs::canonicalize(path) {
+        Ok(canonical) => canonical,
+        Err(_) => path.to_owned(),
+    };
+    if let Some(start) = includes
+        .iter()
+        .position(|(included, _)| *included == canonical)
+    {
+        let mut cycle: Vec<PathBuf> = includes[start..]
+            .iter()
+            .map(|(_, path)| path.clone())
+            .collect();
+        cycle.push(path.to_owned());
+        return Err(error::BrewfileError::IncludeCycle(cycle));
+    }
+
+    // Read file
+    let source = match std::fs::read_to_string(path) {
         Ok(source) => source,
-        Err(error) => return Err(error::BrewfileError::ReadBrewfileError(error)),
+        Err(error) => {
+            return Err(error::BrewfileError::ReadBrewfileError(
+                path.to_owned(),
+                error,
+            ))
+        }
     };
 
+    file.sources.push(Source {
+        path: path.to_owned(),
+        source,
+    });
+    file.current = file.sources.len() - 1;
+
+    includes.push((canonical, path.to_owned()));
+    let result = parse_tokens(file, variables, handle_command, includes);
+    includes.pop();
+    result
+}
+
+fn parse_tokens<F: FnMut(&str, Vec<String>) -> Result<(), error::BrewfileError>>(
+    file: &mut File,
+    variables: &mut Variables,
+    handle_command: &mut F,
+    includes: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), error::BrewfileError> {
+    let mut conditions: Vec<Condition> = Vec::new();
+
     // Tokenize
     let mut tokens = match compiler::lexer::tokenize(
-        source,
+        file.sources[file.current].source.clone(),
         lexer::get_next_token,
         lexer::TokenClass::EndOfFile,
         compiler::lexer::WhitespaceIgnore::AllExceptNewline(lexer::TokenClass::Newline),
     ) {
         Ok(tokens) => tokens,
-        Err(error) => return Err(error::BrewfileError::TokenizeError(error)),
+        Err(error) => {
+            return Err(match error.downcast::<error::BrewfileError>() {
+                Ok(error) => match error.lexer_position() {
+                    Some((text, line, column)) => {
+                        let location = file.location(&text, file.position(line, column));
+                        error::BrewfileError::Located(error, location)
+                    }
+                    None => *error,
+                },
+                Err(error) => error::BrewfileError::TokenizeError(error),
+            })
+        }
     };
 
     // Parse
-    let mut brewfile = Brewfile::new();
     loop {
         let token = tokens.next();
         match token.class() {
-            lexer::TokenClass::EndOfFile => return Ok(brewfile),
+            lexer::TokenClass::EndOfFile => return end_of_file(file, &conditions),
             lexer::TokenClass::Newline => {}
+            lexer::TokenClass::String(command)
+                if command == "if" || command == "else" || command == "end" =>
+            {
+                let position = file.position(token.line(), token.column());
+
+                let last_token = match command.as_str() {
+                    "if" => parse_if(file, &mut tokens, &mut conditions, variables, position)?,
+                    "else" => {
+                        let condition = match conditions.last_mut() {
+                            Some(condition) => condition,
+                            None => {
+                                return Err(located(
+                                    file,
+                                    error::BrewfileError::ElseWithoutIf,
+                                    "else",
+                                    position,
+                                ))
+                            }
+                        };
+                        if condition.in_else {
+                            return Err(located(
+                                file,
+                                error::BrewfileError::DuplicateElse,
+                                "else",
+                                position,
+                            ));
+                        }
+
+                        condition.in_else = true;
+                        condition.active = condition.enclosing_active && !condition.taken;
+                        end_of_line(file, &mut tokens)?
+                    }
+                    _ => {
+                        if conditions.pop().is_none() {
+                            return Err(located(
+                                file,
+                                error::BrewfileError::EndWithoutIf,
+                                "end",
+                                position,
+                            ));
+                        }
+                        end_of_line(file, &mut tokens)?
+                    }
+                };
+
+                if *last_token.class() == lexer::TokenClass::EndOfFile {
+                    return end_of_file(file, &conditions);
+                }
+            }
+            // Skip lines in branches which are not used
+            lexer::TokenClass::String(_)
+                if !conditions
+                    .last()
+                    .map(|condition| condition.active)
+                    .unwrap_or(true) =>
+            {
+                loop {
+                    let token = tokens.next();
+                    match token.class() {
+                        lexer::TokenClass::Newline => break,
+                        lexer::TokenClass::EndOfFile => return end_of_file(file, &conditions),
+                        _ => {}
+                    }
+                }
+            }
             lexer::TokenClass::String(command) => {
+                file.entries.push(Entry {
+                    key: command.to_owned(),
+                    position: file.position(token.line(), token.column()),
+                    parameters: Vec::new(),
+                });
+
+                let variable = match command.as_str() {
+                    "let" => {
+                        let token = tokens.next();
+                        match token.class() {
+                            lexer::TokenClass::String(name)
+                                if crate::config::BUILT_IN_VARIABLES.contains(&name.as_str())
+                                    || name.starts_with("env.") =>
+                            {
+                                let position = file.position(token.line(), token.column());
+                                return Err(located(
+                                    file,
+                                    error::BrewfileError::BuiltInVariable(name.to_owned()),
+                                    name,
+                                    position,
+                                ));
+                            }
+                            lexer::TokenClass::String(name) => Some(name.to_owned()),
+                            _ => return Err(unexpected_token(file, "variable name", &token)),
+                        }
+                    }
+                    _ => None,
+                };
+
                 let token = tokens.next();
                 match token.class() {
                     lexer::TokenClass::Equals => {}
-                    lexer::TokenClass::Newline | lexer::TokenClass::EndOfFile => {
-                        parse_command(command, Vec::new(), &mut brewfile)?;
+                    lexer::TokenClass::Newline | lexer::TokenClass::EndOfFile
+                        if variable.is_none() =>
+                    {
+                        match handle_command(command, Vec::new()) {
+                            Ok(()) => {}
+                            Err(error) => return Err(file.locate(error)),
+                        }
                         match token.class() {
-                            lexer::TokenClass::EndOfFile => return Ok(brewfile),
-                            _ => {}
+                            lexer::TokenClass::EndOfFile => return end_of_file(file, &conditions),
+                            _ => continue,
                         }
                     }
-                    _ => {
-                        return Err(error::BrewfileError::UnexpectedToken(
-                            "equals or newline",
-                            token.to_string(),
-                        ))
-                    }
+                    _ => return Err(unexpected_token(file, "equals or newline", &token)),
                 }
 
-                let mut parameters = Vec::new();
-
                 let last_token = 'parameter_loop: loop {
                     let token = tokens.next();
                     match token.class() {
-                        lexer::TokenClass::String(parameter) => {
-                            parameters.push(parameter.to_owned())
-                        }
-                        _ => {
-                            return Err(error::BrewfileError::UnexpectedToken(
-                                "parameter",
-                                token.to_string(),
-                            ))
+                        lexer::TokenClass::String(value)
+                        | lexer::TokenClass::QuotedString(value, _) => {
+                            let text = token.class().text();
+                            let position = file.position(token.line(), token.column());
+
+                            let values = match variables.substitute(value) {
+                                Ok(values) => values,
+                                Err(error) => {
+                                    let location = file.location(&text, position);
+                                    return Err(error::BrewfileError::Located(
+                                        Box::new(error),
+                                        location,
+                                    ));
+                                }
+                            };
+
+                            for value in values {
+                                file.entries.last_mut().unwrap().parameters.push(Parameter {
+                                    value,
+                                    text: text.clone(),
+                                    position,
+                                })
+                            }
                         }
+                        _ => return Err(unexpected_token(file, "parameter", &token)),
                     }
 
                     let token = tokens.next();
@@ -70,32 +433,230 @@ pub fn parse_brewfile() -> Result<Brewfile, error::BrewfileError> {
                         lexer::TokenClass::Newline | lexer::TokenClass::EndOfFile => {
                             break 'parameter_loop token
                         }
-                        _ => {
-                            return Err(error::BrewfileError::UnexpectedToken(
-                                "comma or newline",
-                                token.to_string(),
-                            ))
-                        }
+                        _ => return Err(unexpected_token(file, "comma or newline", &token)),
                     }
                 };
 
-                parse_command(command, parameters, &mut brewfile)?;
+                let parameters = file
+                    .entries
+                    .last()
+                    .unwrap()
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.value.clone())
+                    .collect();
+
+                match variable {
+                    Some(name) => variables.set(name, parameters),
+                    None if command == "include" => {
+                        parse_include(file, variables, handle_command, includes)?
+                    }
+                    None => match handle_command(command, parameters) {
+                        Ok(()) => {}
+                        Err(error) => return Err(file.locate(error)),
+                    },
+                }
+
+                // The project's name can be used once it has been set
+                if command == "name" {
+                    if let Some(parameter) = file.entries.last().unwrap().parameters.first() {
+                        variables.set("name".to_owned(), vec![parameter.value.clone()]);
+                    }
+                }
 
                 match last_token.class() {
-                    lexer::TokenClass::EndOfFile => return Ok(brewfile),
+                    lexer::TokenClass::EndOfFile => return end_of_file(file, &conditions),
                     _ => {}
                 }
             }
-            _ => {
-                return Err(error::BrewfileError::UnexpectedToken(
-                    "command",
-                    token.to_string(),
-                ))
+            _ => return Err(unexpected_token(file, "command", &token)),
+        }
+    }
+}
+
+// Parses the files named by the last entry, an "include" line. Paths are
+// relative to the directory of the including file.
+fn parse_include<F: FnMut(&str, Vec<String>) -> Result<(), error::BrewfileError>>(
+    file: &mut File,
+    variables: &mut Variables,
+    handle_command: &mut F,
+    includes: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), error::BrewfileError> {
+    let current = file.current;
+    let directory = match file.sources[current].path.parent() {
+        Some(directory) => directory.to_owned(),
+        None => PathBuf::new(),
+    };
+
+    let parameters: Vec<(String, String, Position)> = file
+        .entries
+        .last()
+        .unwrap()
+        .parameters
+        .iter()
+        .map(|parameter| {
+            (
+                parameter.value.clone(),
+                parameter.text.clone(),
+                parameter.position,
+            )
+        })
+        .collect();
+
+    for (value, text, position) in parameters {
+        match parse_source(
+            file,
+            &directory.join(value),
+            variables,
+            handle_command,
+            includes,
+        ) {
+            Ok(()) => {}
+            Err(error) => {
+                let location = file.location(&text, position);
+                return Err(error::BrewfileError::Included(Box::new(error), location));
             }
         }
+        file.current = current;
+    }
+
+    Ok(())
+}
+
+fn located(
+    file: &File,
+    error: error::BrewfileError,
+    text: &str,
+    position: Position,
+) -> error::BrewfileError {
+    let location = file.location(text, position);
+    error::BrewfileError::Located(Box::new(error), location)
+}
+
+// Checks every "if" was ended
+fn end_of_file(file: &File, conditions: &[Condition]) -> Result<(), error::BrewfileError> {
+    match conditions.last() {
+        Some(condition) => Err(located(
+            file,
+            error::BrewfileError::UnterminatedIf,
+            "if",
+            condition.position,
+        )),
+        None => Ok(()),
     }
 }
 
+// Reads the newline or end of file which must end a line
+fn end_of_line(
+    file: &File,
+    tokens: &mut compiler::lexer::TokenStream<lexer::TokenClass>,
+) -> Result<compiler::lexer::Token<lexer::TokenClass>, error::BrewfileError> {
+    let token = tokens.next();
+    match token.class() {
+        lexer::TokenClass::Newline | lexer::TokenClass::EndOfFile => Ok(token),
+        _ => Err(unexpected_token(file, "newline", &token)),
+    }
+}
+
+// Parses "if <variable> ==|!= <value>" and starts a block. Conditions inside
+// unused branches are not evaluated, as their variables may not be defined.
+fn parse_if(
+    file: &File,
+    tokens: &mut compiler::lexer::TokenStream<lexer::TokenClass>,
+    conditions: &mut Vec<Condition>,
+    variables: &Variables,
+    position: Position,
+) -> Result<compiler::lexer::Token<lexer::TokenClass>, error::BrewfileError> {
+    let name_token = tokens.next();
+    let name = match name_token.class() {
+        lexer::TokenClass::String(name) => name.to_owned(),
+        _ => return Err(unexpected_token(file, "variable name", &name_token)),
+    };
+
+    let token = tokens.next();
+    let equal = match token.class() {
+        lexer::TokenClass::DoubleEquals => true,
+        lexer::TokenClass::NotEquals => false,
+        _ => return Err(unexpected_token(file, "'==' or '!='", &token)),
+    };
+
+    let value_token = tokens.next();
+    let value = match value_token.class() {
+        lexer::TokenClass::String(value) | lexer::TokenClass::QuotedString(value, _) => {
+            value.to_owned()
+        }
+        _ => return Err(unexpected_token(file, "value", &value_token)),
+    };
+
+    let last_token = end_of_line(file, tokens)?;
+
+    let enclosing_active = conditions
+        .last()
+        .map(|condition| condition.active)
+        .unwrap_or(true);
+    let taken = match enclosing_active {
+        true => {
+            let name_position = file.position(name_token.line(), name_token.column());
+            let actual = match variables.get(&name) {
+                Some(values) => values.join(" "),
+                None => {
+                    return Err(located(
+                        file,
+                        error::BrewfileError::UndefinedVariable(name.clone()),
+                        &name,
+                        name_position,
+                    ))
+                }
+            };
+
+            let value_position = file.position(value_token.line(), value_token.column());
+            let expected = match variables.substitute(&value) {
+                Ok(values) => values.join(" "),
+                Err(error) => {
+                    return Err(located(
+                        file,
+                        error,
+                        &value_token.class().text(),
+                        value_position,
+                    ))
+                }
+            };
+
+            (actual == expected) == equal
+        }
+        false => false,
+    };
+
+    conditions.push(Condition {
+        active: enclosing_active && taken,
+        enclosing_active,
+        taken,
+        in_else: false,
+        position,
+    });
+
+    Ok(last_token)
+}
+
+fn unexpected_token(
+    file: &File,
+    expected: &'static str,
+    token: &compiler::lexer::Token<lexer::TokenClass>,
+) -> error::BrewfileError {
+    let location = file.location(
+        &token.class().text(),
+        file.position(token.line(), token.column()),
+    );
+
+    error::BrewfileError::Located(
+        Box::new(error::BrewfileError::UnexpectedToken(
+            expected,
+            token.to_string(),
+        )),
+        location,
+    )
+}
+
 fn parse_command(
     command: &str,
     parameters: Vec<String>,
@@ -128,6 +689,8 @@ fn parse_command(
             let brew_type = match brew_type_str.as_str() {
                 "executable" => BrewType::Executable,
                 "library" => BrewType::Library,
+                "shared_library" => BrewType::SharedLibrary,
+                "binary" => BrewType::Binary,
                 "group" => BrewType::Group,
                 _ => {
                     return Err(error::BrewfileError::UnknownBrewType(
@@ -171,6 +734,55 @@ fn parse_command(
 
             Ok(())
         }
+        _ if is_flags_key(command) => {
+            parse_flags_command(command, parameters, brewfile.flags_mut())
+        }
+        "version" => {
+            if parameters.len() != 1 {
+                return Err(error::BrewfileError::InvalidNumberOfParameters(
+                    command.to_owned(),
+                    1,
+                    parameters.len(),
+                ));
+            }
+
+            brewfile.set_version(parameters.first().unwrap().to_owned())?;
+
+            Ok(())
+        }
+        "linker_script" | "entry" | "output" | "install_dir" | "build_dir" => {
+            if parameters.len() != 1 {
+                return Err(error::BrewfileError::InvalidNumberOfParameters(
+                    command.to_owned(),
+                    1,
+                    parameters.len(),
+                ));
+            }
+
+            let parameter = parameters.into_iter().next().unwrap();
+            match command {
+                "linker_script" => brewfile.set_linker_script(PathBuf::from(parameter))?,
+                "entry" => brewfile.set_entry(parameter)?,
+                "output" => brewfile.set_output(parameter)?,
+                "build_dir" => brewfile.set_build_dir(PathBuf::from(parameter))?,
+                _ => brewfile.set_install_dir(PathBuf::from(parameter))?,
+            }
+
+            Ok(())
+        }
+        "toolchain" => {
+            if parameters.len() != 1 {
+                return Err(error::BrewfileError::InvalidNumberOfParameters(
+                    command.to_owned(),
+                    1,
+                    parameters.len(),
+                ));
+            }
+
+            brewfile.set_toolchain(PathBuf::from(parameters.first().unwrap()))?;
+
+            Ok(())
+        }
         "priority" => {
             if parameters.len() == 0 {
                 return Err(error::BrewfileError::AtleastParameters(
@@ -186,6 +798,41 @@ fn parse_command(
 
             Ok(())
         }
+        _ if command.starts_with("extension.") => {
+            // "extension.<extension> = <language or ignore>"
+            let extension = &command["extension.".len()..];
+            if extension.is_empty() {
+                return Err(error::BrewfileError::InvalidExtensionKey(
+                    command.to_owned(),
+                ));
+            }
+
+            if parameters.len() != 1 {
+                return Err(error::BrewfileError::InvalidNumberOfParameters(
+                    command.to_owned(),
+                    1,
+                    parameters.len(),
+                ));
+            }
+
+            let class = FileClass::parse(parameters.first().unwrap())?;
+            brewfile.set_extension(extension.to_owned(), class);
+
+            Ok(())
+        }
+        _ if command.starts_with("profile.") => {
+            // "profile.<name>.<flags key>"
+            let (profile, key) = match command["profile.".len()..].rsplit_once('.') {
+                Some((profile, key)) if !profile.is_empty() && is_flags_key(key) => (profile, key),
+                _ => return Err(error::BrewfileError::InvalidProfileKey(command.to_owned())),
+            };
+
+            parse_flags_command(
+                key,
+                parameters,
+                brewfile.profile_flags_mut(profile.to_owned()),
+            )
+        }
         _ => {
             if parameters.len() != 3 {
                 return Err(error::BrewfileError::InvalidNumberOfParameters(
@@ -211,3 +858,398 @@ fn parse_command(
         }
     }
 }
+
+fn is_flags_key(key: &str) -> bool {
+    matches!(
+        key,
+        "cflags" | "cxxflags" | "asmflags" | "ldflags" | "defines" | "include_dirs"
+    )
+}
+
+fn parse_flags_command(
+    command: &str,
+    parameters: Vec<String>,
+    flags: &mut Flags,
+) -> Result<(), error::BrewfileError> {
+    if parameters.is_empty() {
+        return Err(error::BrewfileError::AtleastParameters(
+            command.to_owned(),
+            1,
+            parameters.len(),
+        ));
+    }
+
+    match command {
+        "cflags" => flags.add_compiler_flags(Language::C, parameters),
+        "cxxflags" => flags.add_compiler_flags(Language::CPlusPlus, parameters),
+        "asmflags" => flags.add_compiler_flags(Language::Assembly, parameters),
+        "ldflags" => flags.add_linker_flags(parameters),
+        "defines" => flags.add_defines(parameters),
+        "include_dirs" => {
+            flags.add_include_dirs(parameters.into_iter().map(PathBuf::from).collect())
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn parse_toolchain_command(
+    command: &str,
+    parameters: Vec<String>,
+    toolchain: &mut Toolchain,
+) -> Result<(), error::BrewfileError> {
+    let (tool, flags) = match command {
+        "c_compiler" => (toolchain.compiler_mut(Language::C), false),
+        "c_flags" => (toolchain.compiler_mut(Language::C), true),
+        "cpp_compiler" => (toolchain.compiler_mut(Language::CPlusPlus), false),
+        "cpp_flags" => (toolchain.compiler_mut(Language::CPlusPlus), true),
+        "assembler" => (toolchain.compiler_mut(Language::Assembly), false),
+        "assembler_flags" => (toolchain.compiler_mut(Language::Assembly), true),
+        "linker" => (toolchain.linker_mut(), false),
+        "linker_flags" => (toolchain.linker_mut(), true),
+        "archiver" => (toolchain.archiver_mut(), false),
+        "archiver_flags" => (toolchain.archiver_mut(), true),
+        "objcopy" => (toolchain.objcopy_mut(), false),
+        "objcopy_flags" => (toolchain.objcopy_mut(), true),
+        _ => {
+            return Err(error::BrewfileError::UnknownToolchainKey(
+                command.to_owned(),
+            ))
+        }
+    };
+
+    if flags {
+        tool.set_flags(parameters);
+    } else {
+        if parameters.len() != 1 {
+            return Err(error::BrewfileError::InvalidNumberOfParameters(
+                command.to_owned(),
+                1,
+                parameters.len(),
+            ));
+        }
+
+        tool.set_executable(parameters.into_iter().next().unwrap());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error::BrewfileError, parse_brewfile};
+    use crate::brewfile::Brewfile;
+    use std::path::{Path, PathBuf};
+
+    // Returns an empty directory for one test
+    fn directory(test: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("brew-parser-{}-{}", std::process::id(), test));
+        std::fs::remove_dir_all(&directory).ok();
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    // Writes "files" into "directory" and parses the one named "brewfile"
+    fn parse(
+        directory: &Path,
+        files: &[(&str, &str)],
+        arguments: &[&str],
+    ) -> Result<Brewfile, BrewfileError> {
+        for (name, contents) in files {
+            let path = directory.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let arguments = std::iter::once("brew")
+            .chain(arguments.iter().copied())
+            .map(|argument| argument.to_owned())
+            .collect();
+        let options = crate::arguments::parse_arguments(arguments).unwrap();
+        parse_brewfile(&directory.join("brewfile"), &options)
+    }
+
+    // Parses a brewfile which is expected to fail and returns the message
+    fn error(test: &str, files: &[(&str, &str)]) -> (PathBuf, String) {
+        let directory = directory(test);
+        let message = match parse(&directory, files, &[]) {
+            Ok(_) => panic!("{} parsed without an error", test),
+            Err(error) => error.to_string(),
+        };
+        std::fs::remove_dir_all(&directory).ok();
+        (directory, message)
+    }
+
+    // Returns the project name a brewfile which is expected to parse sets
+    fn name(test: &str, source: &str, arguments: &[&str]) -> String {
+        let directory = directory(test);
+        let brewfile = parse(&directory, &[("brewfile", source)], arguments).unwrap();
+        std::fs::remove_dir_all(&directory).ok();
+        brewfile.name().unwrap().to_owned()
+    }
+
+    #[test]
+    fn nested_blocks_in_unused_branch() {
+        let source = "\
+let mode = debug
+if mode == release
+    if undefined == x
+        name = wrong
+    else
+        name = wrong
+    end
+    dependencies = ${undefined}
+else
+    if mode != debug
+        name = wrong
+    else
+        name = right
+    end
+end
+";
+        assert_eq!(name("nested", source, &[]), "right");
+    }
+
+    #[test]
+    fn undefined_variable_in_used_branch() {
+        let (directory, message) = error("undefined", &[("brewfile", "if mode == debug\nend\n")]);
+        assert_eq!(
+            message,
+            format!(
+                "{}:1:4: Undefined variable \"mode\" in brewfile\n    if mode == debug\n       ^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+    }
+
+    #[test]
+    fn duplicate_else() {
+        let (directory, message) = error(
+            "duplicate-else",
+            &[("brewfile", "let a = 1\nif a == 1\nelse\n  else\nend\n")],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:4:3: \"if\" with more than one \"else\" in brewfile\n      else\n      ^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+    }
+
+    #[test]
+    fn else_and_end_without_if() {
+        let (directory, message) = error("else-without-if", &[("brewfile", "else\n")]);
+        assert_eq!(
+            message,
+            format!(
+                "{}:1:1: \"else\" without a matching \"if\" in brewfile\n    else\n    ^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+
+        let (directory, message) = error(
+            "end-without-if",
+            &[("brewfile", "let a = 1\nif a == 1\nend\nend\n")],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:4:1: \"end\" without a matching \"if\" in brewfile\n    end\n    ^^^",
+                directory.join("brewfile").display()
+            )
+        );
+    }
+
+    #[test]
+    fn unterminated_if() {
+        let (directory, message) = error(
+            "unterminated-if",
+            &[(
+                "brewfile",
+                "let a = 1\nif a == 1\n  if a == 2\n  end\nname = app\n",
+            )],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:2:1: \"if\" without a matching \"end\" in brewfile\n    if a == 1\n    ^^",
+                directory.join("brewfile").display()
+            )
+        );
+    }
+
+    #[test]
+    fn define_overrides_let() {
+        let source = "\
+let mode = debug
+if mode == release
+    name = ${mode}
+end
+";
+        assert_eq!(name("define", source, &["-D", "mode=release"]), "release");
+        assert_eq!(
+            name("define-unused", "let mode = debug\nname = ${mode}\n", &[]),
+            "debug"
+        );
+    }
+
+    #[test]
+    fn include_cycle_names_every_file() {
+        let (directory, message) = error(
+            "include-cycle",
+            &[
+                ("brewfile", "include = a.brew\n"),
+                ("a.brew", "name = app\n  include = brewfile\n"),
+            ],
+        );
+        let brewfile = directory.join("brewfile");
+        let included = directory.join("a.brew");
+        assert_eq!(
+            message,
+            format!(
+                "Include cycle in brewfile: {} -> {} -> {}\n\
+                 {}:2:13: included from here\n      include = brewfile\n                ^^^^^^^^\n\
+                 {}:1:11: included from here\n    include = a.brew\n              ^^^^^^",
+                brewfile.display(),
+                included.display(),
+                brewfile.display(),
+                included.display(),
+                brewfile.display()
+            )
+        );
+    }
+
+    #[test]
+    fn nested_include_is_relative_to_includer() {
+        let directory = directory("include-nested");
+        let brewfile = parse(
+            &directory,
+            &[
+                ("brewfile", "include = sub/a.brew\n"),
+                ("sub/a.brew", "include = b.brew\n"),
+                ("sub/b.brew", "name = nested\n"),
+                ("b.brew", "name = wrong\n"),
+            ],
+            &[],
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&directory).ok();
+        assert_eq!(brewfile.name(), Some("nested"));
+    }
+
+    #[test]
+    fn error_in_included_file_reports_both_locations() {
+        let (directory, message) = error(
+            "include-error",
+            &[
+                ("brewfile", "name = app\ninclude = common.brew\n"),
+                ("common.brew", "languages = c\ntype = bogus\n"),
+            ],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:2:8: Unknown brew type \"bogus\" in brewfile\n    type = bogus\n           ^^^^^\n\
+                 {}:2:11: included from here\n    include = common.brew\n              ^^^^^^^^^^^",
+                directory.join("common.brew").display(),
+                directory.join("brewfile").display()
+            )
+        );
+    }
+
+    #[test]
+    fn duplicate_key_reports_both_definitions() {
+        let (directory, message) = error(
+            "duplicate-key",
+            &[("brewfile", "name = a\ntype = executable\nname = b\n")],
+        );
+        let path = directory.join("brewfile");
+        assert_eq!(
+            message,
+            format!(
+                "{}:3:1: Attempting to specify more than one name in brewfile\n    name = b\n    ^^^^\n\
+                 {}:1:1: first defined here\n    name = a\n    ^^^^",
+                path.display(),
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn duplicate_list_items_point_to_the_items() {
+        // The underline keeps the tab so it lines up with the item
+        let (directory, message) = error(
+            "duplicate-language",
+            &[("brewfile", "languages = c, cpp,\tc\n")],
+        );
+        let path = directory.join("brewfile");
+        assert_eq!(
+            message,
+            format!(
+                "{}:1:21: Attempting to specify language 'C' twice in brewfile\n    languages = c, cpp,\tc\n                       \t^\n\
+                 {}:1:13: first defined here\n    languages = c, cpp,\tc\n                ^",
+                path.display(),
+                path.display()
+            )
+        );
+
+        let (directory, message) = error(
+            "duplicate-dependency",
+            &[(
+                "brewfile",
+                "dependencies = foo, bar\ndependencies = baz, foo\n",
+            )],
+        );
+        let path = directory.join("brewfile");
+        assert_eq!(
+            message,
+            format!(
+                "{}:2:21: Attempting to specify dependency 'foo' twice in brewfile\n    dependencies = baz, foo\n                        ^^^\n\
+                 {}:1:16: first defined here\n    dependencies = foo, bar\n                   ^^^",
+                path.display(),
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn errors_point_to_the_parameter_or_key() {
+        let (directory, message) = error(
+            "unknown-language",
+            &[("brewfile", "languages = c, fortran\n")],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:1:16: Unknown language \"fortran\" in brewfile\n    languages = c, fortran\n                   ^^^^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+
+        let (directory, message) = error("parameter-count", &[("brewfile", "name = a, b\n")]);
+        assert_eq!(
+            message,
+            format!(
+                "{}:1:1: name requires 1 paramters but 2 are specified in brewfile\n    name = a, b\n    ^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+
+        // Keys which are only invalid in combination point to the key even
+        // when it is not the last entry
+        let (directory, message) = error(
+            "version",
+            &[("brewfile", "type = library\nversion = 1.0\nname = x\n")],
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{}:2:1: Library projects cannot have a version\n    version = 1.0\n    ^^^^^^^",
+                directory.join("brewfile").display()
+            )
+        );
+    }
+}
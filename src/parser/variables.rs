@@ -0,0 +1,195 @@
+use super::error::BrewfileError;
+use crate::arguments::Options;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Values available for "${name}" substitution. Each variable holds a list so
+// that a set of flags can be substituted as separate parameters.
+pub struct Variables {
+    values: BTreeMap<String, Vec<String>>,
+    // Variables defined on the command line, which "let" cannot change
+    defined: BTreeSet<String>,
+}
+
+impl Variables {
+    // Creates the built-in variables describing the build
+    pub fn new(options: &Options) -> Self {
+        let mut values = BTreeMap::new();
+        values.insert(
+            "sysroot".to_owned(),
+            vec![options.sysroot().to_string_lossy().into_owned()],
+        );
+        values.insert(
+            "prefix".to_owned(),
+            vec![options.prefix().to_string_lossy().into_owned()],
+        );
+        values.insert("profile".to_owned(), vec![options.profile().to_owned()]);
+
+        let mut defined = BTreeSet::new();
+        for (name, value) in options.defines() {
+            values.insert(name.to_owned(), vec![value.to_owned()]);
+            defined.insert(name.to_owned());
+        }
+
+        Variables { values, defined }
+    }
+
+    pub fn set(&mut self, name: String, values: Vec<String>) {
+        if !self.defined.contains(&name) {
+            self.values.insert(name, values);
+        }
+    }
+
+    // "env.<name>" reads the environment variable <name>
+    pub fn get(&self, name: &str) -> Option<Vec<String>> {
+        match name.strip_prefix("env.") {
+            Some(variable) => match std::env::var(variable) {
+                Ok(value) => Some(vec![value]),
+                Err(_) => None,
+            },
+            None => self.values.get(name).cloned(),
+        }
+    }
+
+    // Replaces every "${name}" in "parameter" and "$$" with "$". A parameter
+    // which is only a reference to a list is replaced by each item, otherwise
+    // the items are joined with spaces.
+    pub fn substitute(&self, parameter: &str) -> Result<Vec<String>, BrewfileError> {
+        let mut result = String::new();
+        let mut chars = parameter.chars();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                result.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('$') => result.push('$'),
+                Some('{') => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(BrewfileError::InvalidSubstitution(
+                                    parameter.to_owned(),
+                                ))
+                            }
+                        }
+                    }
+
+                    let values = match self.get(&name) {
+                        Some(values) => values,
+                        None => return Err(BrewfileError::UndefinedVariable(name)),
+                    };
+
+                    if result.is_empty() && chars.as_str().is_empty() {
+                        return Ok(values);
+                    }
+
+                    result.push_str(&values.join(" "));
+                }
+                _ => return Err(BrewfileError::InvalidSubstitution(parameter.to_owned())),
+            }
+        }
+
+        Ok(vec![result])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Variables;
+    use crate::parser::error::BrewfileError;
+
+    fn variables(arguments: &[&str]) -> Variables {
+        let arguments = std::iter::once("brew")
+            .chain(arguments.iter().copied())
+            .map(|argument| argument.to_owned())
+            .collect();
+        let mut variables = Variables::new(&crate::arguments::parse_arguments(arguments).unwrap());
+        variables.set("flags".to_owned(), vec!["-a".to_owned(), "-b".to_owned()]);
+        variables.set("dir".to_owned(), vec!["src".to_owned()]);
+        variables
+    }
+
+    fn substitute(parameter: &str) -> Vec<String> {
+        variables(&[]).substitute(parameter).unwrap()
+    }
+
+    #[test]
+    fn text_without_references() {
+        assert_eq!(substitute("-Wall"), vec!["-Wall"]);
+        assert_eq!(substitute(""), vec![""]);
+    }
+
+    #[test]
+    fn escaped_dollar() {
+        assert_eq!(substitute("$$"), vec!["$"]);
+        assert_eq!(substitute("a$$b$$"), vec!["a$b$"]);
+        // An escaped reference is left as text rather than substituted again
+        assert_eq!(substitute("$${flags}"), vec!["${flags}"]);
+        assert_eq!(substitute("$$$${dir}"), vec!["$${dir}"]);
+    }
+
+    #[test]
+    fn whole_parameter_reference_expands_to_list() {
+        assert_eq!(substitute("${flags}"), vec!["-a", "-b"]);
+        assert_eq!(substitute("${dir}"), vec!["src"]);
+    }
+
+    #[test]
+    fn reference_in_text_is_joined() {
+        assert_eq!(substitute("-I${dir}/include"), vec!["-Isrc/include"]);
+        assert_eq!(substitute("${flags}x"), vec!["-a -bx"]);
+        assert_eq!(substitute("${dir}${dir}"), vec!["srcsrc"]);
+        assert_eq!(substitute("$$${dir}"), vec!["$src"]);
+    }
+
+    #[test]
+    fn built_in_variables() {
+        let variables = variables(&["--profile", "release", "--prefix", "/usr"]);
+        assert_eq!(
+            variables.substitute("${profile}:${prefix}").unwrap(),
+            vec!["release:/usr"]
+        );
+    }
+
+    #[test]
+    fn environment_variables() {
+        std::env::set_var("BREW_VARIABLES_TEST", "value");
+        assert_eq!(substitute("${env.BREW_VARIABLES_TEST}"), vec!["value"]);
+
+        match variables(&[]).substitute("${env.BREW_VARIABLES_TEST_UNSET}") {
+            Err(BrewfileError::UndefinedVariable(name)) => {
+                assert_eq!(name, "env.BREW_VARIABLES_TEST_UNSET")
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn defines_override_let() {
+        let mut variables = variables(&["-D", "mode=fast"]);
+        variables.set("mode".to_owned(), vec!["slow".to_owned()]);
+        assert_eq!(variables.substitute("${mode}").unwrap(), vec!["fast"]);
+    }
+
+    #[test]
+    fn undefined_variable() {
+        match variables(&[]).substitute("a${missing}b") {
+            Err(BrewfileError::UndefinedVariable(name)) => assert_eq!(name, "missing"),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn invalid_substitutions() {
+        for parameter in ["$", "a$", "$x", "${dir", "${", "a${dir}${"] {
+            match variables(&[]).substitute(parameter) {
+                Err(BrewfileError::InvalidSubstitution(text)) => assert_eq!(text, parameter),
+                result => panic!("{:?} gave {:?}", parameter, result),
+            }
+        }
+    }
+}